@@ -2,10 +2,10 @@
 extern crate criterion;
 extern crate gadgetcoin;
 
-use criterion::{Criterion, Fun};
+use criterion::{Criterion, Fun, ParameterizedBenchmark, Throughput};
 
 use gadgetcoin::eth::benchutils::*;
-use gadgetcoin::eth::ETHBlock;
+use gadgetcoin::eth::{recover_senders, ETHBlock, ETHTxn};
 
 fn thread_sweep(c: &mut Criterion) {
     c.bench_function_over_inputs("Parallel Hashing - Different # of Threads", |b, num_threads| {
@@ -21,6 +21,54 @@ fn thread_sweep(c: &mut Criterion) {
     }, 1..=24);
 }
 
+fn verification_thread_sweep(c: &mut Criterion) {
+    let actors = generate_actors();
+    let txns: Vec<ETHTxn> = TxnGenerator::new(&actors, TOTAL_TXNS).collect();
+    let sweep = ParameterizedBenchmark::new(
+        "Parallel Sender Recovery - Different # of Threads",
+        move |b, num_threads| b.iter(|| recover_senders(&txns, *num_threads)),
+        1..=24,
+    )
+    .throughput(|_| Throughput::Elements(TOTAL_TXNS as u32));
+    c.bench("Sender Recovery", sweep);
+}
+
+fn sequential_parallel_execution(c: &mut Criterion) {
+    let sequential_run = Fun::new("Sequential Execution", |b, _| {
+        let actors = generate_actors();
+        let txns: Vec<ETHTxn> = TxnGenerator::new(&actors, TOTAL_TXNS).collect();
+        b.iter_with_setup(
+            || generate_evmstate(&actors),
+            |mut state| {
+                for txn in &txns {
+                    state.execute_transaction(txn);
+                }
+            },
+        )
+    });
+
+    let batched_run = Fun::new("Parallel Sender Recovery", |b, _| {
+        let actors = generate_actors();
+        let txns: Vec<ETHTxn> = TxnGenerator::new(&actors, TOTAL_TXNS).collect();
+        b.iter_with_setup(
+            || generate_evmstate(&actors),
+            |mut state| state.execute_block(&txns, NUM_THREADS as usize),
+        )
+    });
+
+    let parallel_run = Fun::new("Parallel Execution", |b, _| {
+        let actors = generate_actors();
+        let txns: Vec<ETHTxn> = TxnGenerator::new(&actors, TOTAL_TXNS).collect();
+        b.iter_with_setup(
+            || generate_evmstate(&actors),
+            |mut state| state.execute_block_parallel(&txns, NUM_THREADS as usize),
+        )
+    });
+
+    let functions = vec![sequential_run, batched_run, parallel_run];
+    c.bench_functions("Sequential vs. Parallel Execution", functions, &TOTAL_TXNS);
+}
+
 fn sequential_parallel_comparision(c: &mut Criterion) {
     let sequential_run = Fun::new("Sequential Hashing", |b, _| {
         let actors_seq = generate_actors();
@@ -53,6 +101,7 @@ fn sequential_parallel_comparision(c: &mut Criterion) {
 criterion_group! {
     name = benches;
     config = Criterion::default().sample_size(2);
-    targets = thread_sweep, sequential_parallel_comparision
+    targets = thread_sweep, verification_thread_sweep, sequential_parallel_execution,
+        sequential_parallel_comparision
 }
 criterion_main!(benches);
@@ -0,0 +1,57 @@
+#[macro_use]
+extern crate criterion;
+extern crate gadgetcoin;
+
+use criterion::{Criterion, Fun};
+
+use gadgetcoin::eth::{ExecutionContext, GasSchedule, Op, ThreadedProgram, Wei};
+use gadgetcoin::lang;
+
+// nested loops: 62,500 trips around the inner loop
+fn loop_heavy_code() -> Vec<Op> {
+    lang::compile_ops(
+        "let i = 0;
+         let j = 0;
+         while i < 250 {
+             j = 0;
+             while j < 250 {
+                 j = j + 1;
+                 value += 1;
+             }
+             i = i + 1;
+         }",
+    )
+    .unwrap()
+}
+
+fn run(code: &[Op], block_gas: bool) {
+    let mut engine = ExecutionContext::new(100_000_000, code.to_vec(), Wei::from_wei(0));
+    engine.set_block_gas_charging(block_gas);
+    assert!(engine.finish_executing());
+}
+
+fn gas_charging_comparison(c: &mut Criterion) {
+    let per_op = Fun::new("Per-op Gas", |b, code: &Vec<Op>| {
+        b.iter(|| run(code, false))
+    });
+    let per_block = Fun::new("Basic-block Gas", |b, code: &Vec<Op>| {
+        b.iter(|| run(code, true))
+    });
+    // compiled once, as a node would cache it per contract
+    let threaded = Fun::new("Threaded", |b, code: &Vec<Op>| {
+        let program = ThreadedProgram::compile(code, &GasSchedule::default());
+        b.iter(|| assert!(program.run(100_000_000, Wei::from_wei(0)).succeeded))
+    });
+    c.bench_functions(
+        "Loop-heavy Interpretation",
+        vec![per_op, per_block, threaded],
+        loop_heavy_code(),
+    );
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(10);
+    targets = gas_charging_comparison
+}
+criterion_main!(benches);
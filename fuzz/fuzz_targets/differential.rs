@@ -0,0 +1,45 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use gadgetcoin::eth::{
+    ExecutionContext, Gas, GasSchedule, Interpreter, Op, ThreadedInterpreter, Wei,
+};
+
+// per-op gas in ExecutionContext is the reference; block gas charging
+// and the threaded backend must agree with it exactly
+fuzz_target!(|data: &[u8]| {
+    if data.len() < 3 {
+        return;
+    }
+    let gaslimit = Gas::from(u16::from_le_bytes([data[0], data[1]]));
+    let value = Wei::from_wei(u128::from(data[2]));
+    let code = Op::from_bytes(&data[3..]);
+
+    let run = |block_gas: bool| {
+        let mut engine = ExecutionContext::new(gaslimit, code.clone(), value);
+        engine.set_block_gas_charging(block_gas);
+        let succeeded = engine.finish_executing();
+        (
+            succeeded,
+            engine.get_gas_left(),
+            engine.get_value(),
+            engine.get_stack().to_vec(),
+            engine.get_logs().to_vec(),
+        )
+    };
+    let reference = run(false);
+    assert_eq!(run(true), reference);
+
+    let threaded =
+        ThreadedInterpreter.execute(gaslimit, &code, value, &GasSchedule::shared_default());
+    assert_eq!(
+        (
+            threaded.succeeded,
+            threaded.gas_left,
+            threaded.value,
+            threaded.stack,
+            threaded.logs
+        ),
+        reference
+    );
+});
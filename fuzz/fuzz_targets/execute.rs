@@ -0,0 +1,17 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use gadgetcoin::eth::{ExecutionContext, Gas, Op, Wei};
+
+// the first two bytes pick the gas limit and the third the value,
+// which keeps every run short
+fuzz_target!(|data: &[u8]| {
+    if data.len() < 3 {
+        return;
+    }
+    let gaslimit = Gas::from(u16::from_le_bytes([data[0], data[1]]));
+    let value = Wei::from_wei(u128::from(data[2]));
+    let mut engine = ExecutionContext::new(gaslimit, Op::from_bytes(&data[3..]), value);
+    engine.finish_executing();
+    assert!(engine.get_gas_left() <= gaslimit);
+});
@@ -0,0 +1,13 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use gadgetcoin::eth::Op;
+
+fuzz_target!(|data: &[u8]| {
+    let ops = Op::from_bytes(data);
+    // every op but a trailing operand-less PUSH1 survives a round trip
+    let bytes = Op::to_bytes(&ops);
+    assert!(data.starts_with(&bytes));
+    assert!(data.len() - bytes.len() <= 1);
+    assert_eq!(Op::from_bytes(&bytes), ops);
+});
@@ -0,0 +1,177 @@
+use std::fs;
+use std::io;
+use std::io::{BufRead, Write};
+
+use eth::asm;
+use eth::debugger::Debugger;
+use eth::disasm;
+use eth::hexutil;
+use eth::{ETHTxn, Gas, Op, Wei};
+use lang;
+
+const DEFAULT_GAS: Gas = 1_000_000;
+
+const USAGE: &str = "usage:
+  gadgetcoin                                  run the hashing benchmarks
+  gadgetcoin debug <hex> [--gas N] [--value AMOUNT]
+  gadgetcoin debug --file <path> [--gas N] [--value AMOUNT]
+  gadgetcoin debug --txn <hash> --store <path> [--gas N] [--value AMOUNT]
+  gadgetcoin disasm <hex>
+  gadgetcoin disasm --file <path>
+  gadgetcoin disasm --txn <hash> --store <path>
+  gadgetcoin asm <path>
+  gadgetcoin compile <path>";
+
+/// Dispatches a subcommand; `args` excludes the program name.
+pub fn run(args: &[String]) -> Result<(), String> {
+    match args.first().map(String::as_str) {
+        Some("debug") => debug(&args[1..]),
+        Some("disasm") => {
+            let options = parse_code_options(&args[1..])?;
+            print!("{}", disasm::disassemble(&options.code));
+            Ok(())
+        }
+        Some("asm") => {
+            let path = args.get(1).ok_or_else(|| USAGE.to_string())?;
+            let source = fs::read_to_string(path)
+                .map_err(|err| format!("couldn't read {}: {}", path, err))?;
+            let code = asm::assemble(&source).map_err(|err| err.to_string())?;
+            println!("{}", hexutil::encode(&code));
+            Ok(())
+        }
+        Some("compile") => {
+            let path = args.get(1).ok_or_else(|| USAGE.to_string())?;
+            let source = fs::read_to_string(path)
+                .map_err(|err| format!("couldn't read {}: {}", path, err))?;
+            let code = lang::compile(&source).map_err(|err| err.to_string())?;
+            println!("{}", hexutil::encode(&code));
+            Ok(())
+        }
+        Some("help") | Some("--help") | Some("-h") => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        Some(other) => Err(format!("unknown command '{}'\n{}", other, USAGE)),
+        None => Err(USAGE.to_string()),
+    }
+}
+
+// options shared by the commands that run code
+struct CodeOptions {
+    code: Vec<u8>,
+    gas: Gas,
+    value: Wei,
+}
+
+fn parse_code_options(args: &[String]) -> Result<CodeOptions, String> {
+    let mut code = None;
+    let mut gas = None;
+    let mut value = None;
+    let mut txn_hash = None;
+    let mut store = None;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let mut flag_value = || {
+            iter.next()
+                .ok_or_else(|| format!("missing value for {}", arg))
+        };
+        match arg.as_str() {
+            "--gas" => {
+                gas = Some(
+                    flag_value()?
+                        .parse()
+                        .map_err(|_| "--gas expects an integer".to_string())?,
+                )
+            }
+            "--value" => {
+                // wei unless a unit is given, as in "1.5 ether"
+                value = Some(
+                    flag_value()?
+                        .parse()
+                        .map_err(|err| format!("--value: {}", err))?,
+                )
+            }
+            "--txn" => txn_hash = Some(hexutil::decode(flag_value()?)?),
+            "--store" => store = Some(flag_value()?),
+            "--file" => {
+                // code as hex
+                let path = flag_value()?;
+                let contents = fs::read_to_string(path)
+                    .map_err(|err| format!("couldn't read {}: {}", path, err))?;
+                code = Some(hexutil::decode(&contents)?);
+            }
+            hex => code = Some(hexutil::decode(hex)?),
+        }
+    }
+    if let Some(hash) = txn_hash {
+        // a stored transaction's gas limit and value, unless overridden
+        let path = store.ok_or_else(|| "--txn needs a --store to look in".to_string())?;
+        let txn = find_txn(path, &hash)?;
+        return Ok(CodeOptions {
+            code: txn.code,
+            gas: gas.unwrap_or(txn.gaslimit),
+            value: value.unwrap_or(txn.value),
+        });
+    }
+    match code {
+        Some(code) => Ok(CodeOptions {
+            code,
+            gas: gas.unwrap_or(DEFAULT_GAS),
+            value: value.unwrap_or_default(),
+        }),
+        None => Err(format!("no code given\n{}", USAGE)),
+    }
+}
+
+// a store holds one signed transaction per line, RLP encoded as hex
+fn find_txn(path: &str, hash: &[u8]) -> Result<ETHTxn, String> {
+    let contents =
+        fs::read_to_string(path).map_err(|err| format!("couldn't read {}: {}", path, err))?;
+    for (number, line) in contents.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let txn = ETHTxn::from_rlp(&hexutil::decode(line)?)
+            .map_err(|err| format!("{} line {}: {}", path, number + 1, err))?;
+        if txn.hash()[..] == *hash {
+            return Ok(txn);
+        }
+    }
+    Err(format!(
+        "no transaction {} in {}",
+        hexutil::encode(hash),
+        path
+    ))
+}
+
+fn debug(args: &[String]) -> Result<(), String> {
+    let options = parse_code_options(args)?;
+    let mut debugger = Debugger::new(options.gas, Op::from_bytes(&options.code), options.value);
+    println!("{}", debugger.describe());
+
+    let stdin = io::stdin();
+    loop {
+        print!("(gadget) ");
+        io::stdout().flush().map_err(|err| err.to_string())?;
+        let mut line = String::new();
+        if stdin
+            .lock()
+            .read_line(&mut line)
+            .map_err(|err| err.to_string())?
+            == 0
+        {
+            // end of input
+            println!();
+            return Ok(());
+        }
+        match line.trim() {
+            "q" | "quit" => return Ok(()),
+            command => {
+                let output = debugger.run_command(command);
+                if !output.is_empty() {
+                    println!("{}", output);
+                }
+            }
+        }
+    }
+}
@@ -1,4 +1,5 @@
 pub type ETHAddress = [u8; 20];
 //type BlockHash = ring::digest::Digest;
 pub type BlockHash = [u8; 32];
+pub type TxnHash = [u8; 32];
 pub type ProofOfWork = u32;
@@ -0,0 +1,491 @@
+use std::collections::BTreeSet;
+
+use super::gas::Gas;
+use super::gasschedule::GasSchedule;
+use super::ops::Op;
+
+// stack heights are tracked up to here; anything taller is treated as "tall enough"
+const HEIGHT_CAP: usize = 1024;
+
+/// What static analysis could prove about a piece of code. All positions are
+/// op indices, the same numbering JUMP uses.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Analysis {
+    pub unreachable: Vec<usize>,
+    // ops that underflow the stack on every path that reaches them
+    pub stack_underflows: Vec<usize>,
+    // jumps whose target is a constant past the end of the code
+    pub invalid_jumps: Vec<usize>,
+    // reachable INVALID ops
+    pub invalid_ops: Vec<usize>,
+    // some reachable path runs off the end of the code
+    pub falls_off_end: bool,
+    // a reachable jump takes its target from a computed value. Nothing
+    // is reported as unreachable or underflowing when this is set.
+    pub dynamic_jumps: bool,
+    // worst-case gas, only known when the reachable code has no loops
+    pub max_gas: Option<Gas>,
+    // false only if no execution can reach a STOP or RETURN
+    pub can_succeed: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Exit {
+    Stop,
+    Fail,
+    Block(usize),
+}
+
+struct Block {
+    start: usize,
+    end: usize,
+    exits: Vec<Exit>,
+}
+
+/// Analyzes decoded code, as produced by `Op::from_bytes`, with gas charged
+/// by `schedule`.
+pub fn analyze(code: &[Op], schedule: &GasSchedule) -> Analysis {
+    if code.is_empty() {
+        // empty code stops straight away
+        return Analysis {
+            unreachable: vec![],
+            stack_underflows: vec![],
+            invalid_jumps: vec![],
+            invalid_ops: vec![],
+            falls_off_end: false,
+            dynamic_jumps: false,
+            max_gas: Some(0),
+            can_succeed: true,
+        };
+    }
+
+    let targets = constant_targets(code);
+    let (blocks, block_of) = build_blocks(code, &targets);
+
+    let mut reachable = vec![false; blocks.len()];
+    let mut work = vec![0];
+    reachable[0] = true;
+    while let Some(b) = work.pop() {
+        for exit in &blocks[b].exits {
+            if let Exit::Block(next) = *exit {
+                if !reachable[next] {
+                    reachable[next] = true;
+                    work.push(next);
+                }
+            }
+        }
+    }
+
+    let dynamic_jumps = blocks.iter().zip(&reachable).any(|(block, &live)| {
+        live && is_jump(code[block.end - 1]) && targets[block.end - 1].is_none()
+    });
+    let invalid_jumps = blocks
+        .iter()
+        .zip(&reachable)
+        .filter(|&(_, &live)| live)
+        .map(|(block, _)| block.end - 1)
+        .filter(|&i| targets[i].is_some_and(|target| target >= code.len()))
+        .collect();
+    let invalid_ops = ops_where(&blocks, &reachable, |i| matches!(code[i], Op::INVALID(_)));
+    let falls_off_end = blocks.iter().zip(&reachable).any(|(block, &live)| {
+        live && block.end == code.len() && falls_through(code[block.end - 1])
+    });
+
+    if dynamic_jumps {
+        // a computed jump could land anywhere with any stack
+        return Analysis {
+            unreachable: vec![],
+            stack_underflows: vec![],
+            invalid_jumps,
+            invalid_ops,
+            falls_off_end,
+            dynamic_jumps,
+            max_gas: None,
+            can_succeed: true,
+        };
+    }
+
+    let stack_underflows = find_underflows(code, &blocks, &reachable);
+    let unreachable = (0..code.len())
+        .filter(|&i| !reachable[block_of[i]])
+        .collect();
+    let max_gas = max_gas(code, &blocks, schedule);
+    let doomed: BTreeSet<usize> = stack_underflows.iter().map(|&i| block_of[i]).collect();
+    let can_succeed = can_reach_stop(&blocks, &doomed);
+
+    Analysis {
+        unreachable,
+        stack_underflows,
+        invalid_jumps,
+        invalid_ops,
+        falls_off_end,
+        dynamic_jumps,
+        max_gas,
+        can_succeed,
+    }
+}
+
+fn is_jump(op: Op) -> bool {
+    matches!(op, Op::JUMP | Op::JUMPI)
+}
+
+fn falls_through(op: Op) -> bool {
+    !matches!(op, Op::STOP | Op::RETURN | Op::JUMP | Op::INVALID(_))
+}
+
+// A jump has a constant target when it directly follows a PUSH1 and
+// nothing jumps straight to the jump itself, skipping the push.
+fn constant_targets(code: &[Op]) -> Vec<Option<usize>> {
+    let mut targets: Vec<Option<usize>> = code
+        .iter()
+        .enumerate()
+        .map(|(i, op)| match (i.checked_sub(1).map(|p| code[p]), op) {
+            (Some(Op::PUSH1(target)), Op::JUMP) | (Some(Op::PUSH1(target)), Op::JUMPI) => {
+                Some(usize::from(target))
+            }
+            _ => None,
+        })
+        .collect();
+    let landing: BTreeSet<usize> = targets.iter().flatten().cloned().collect();
+    for i in landing {
+        if i < code.len() && is_jump(code[i]) {
+            targets[i] = None;
+        }
+    }
+    targets
+}
+
+fn build_blocks(code: &[Op], targets: &[Option<usize>]) -> (Vec<Block>, Vec<usize>) {
+    let mut leaders = BTreeSet::new();
+    leaders.insert(0);
+    for (i, op) in code.iter().enumerate() {
+        if let Some(target) = targets[i] {
+            if target < code.len() {
+                leaders.insert(target);
+            }
+        }
+        if !falls_through(*op) || is_jump(*op) {
+            leaders.insert(i + 1);
+        }
+    }
+    leaders.retain(|&i| i < code.len());
+
+    let starts: Vec<usize> = leaders.into_iter().collect();
+    let mut block_of = vec![0; code.len()];
+    let mut blocks = vec![];
+    for (b, &start) in starts.iter().enumerate() {
+        let end = starts.get(b + 1).cloned().unwrap_or(code.len());
+        for slot in &mut block_of[start..end] {
+            *slot = b;
+        }
+        blocks.push(Block {
+            start,
+            end,
+            exits: vec![],
+        });
+    }
+
+    let to_exit = |i: usize, block_of: &[usize]| {
+        if i < code.len() {
+            Exit::Block(block_of[i])
+        } else {
+            Exit::Fail
+        }
+    };
+    for block in &mut blocks {
+        let last = block.end - 1;
+        let mut exits = vec![];
+        match code[last] {
+            Op::STOP | Op::RETURN => exits.push(Exit::Stop),
+            Op::INVALID(_) => exits.push(Exit::Fail),
+            op => {
+                if falls_through(op) {
+                    exits.push(to_exit(block.end, &block_of));
+                }
+                // dynamic targets are handled by the caller giving up
+                if let Some(target) = targets[last] {
+                    exits.push(to_exit(target, &block_of));
+                }
+            }
+        }
+        block.exits = exits;
+    }
+    (blocks, block_of)
+}
+
+fn ops_where<F: Fn(usize) -> bool>(blocks: &[Block], reachable: &[bool], pred: F) -> Vec<usize> {
+    blocks
+        .iter()
+        .zip(reachable)
+        .filter(|&(_, &live)| live)
+        .flat_map(|(block, _)| block.start..block.end)
+        .filter(|&i| pred(i))
+        .collect()
+}
+
+// Propagates the tallest possible stack into each block. If even the
+// tallest stack is too short for an op, every path to it underflows.
+fn find_underflows(code: &[Op], blocks: &[Block], reachable: &[bool]) -> Vec<usize> {
+    let mut entry: Vec<Option<usize>> = vec![None; blocks.len()];
+    entry[0] = Some(0);
+    let mut work = vec![0];
+    while let Some(b) = work.pop() {
+        let mut height = match entry[b] {
+            Some(height) => height,
+            None => continue,
+        };
+        let mut underflowed = false;
+        for op in &code[blocks[b].start..blocks[b].end] {
+            let (pops, pushes) = op.stack_effect();
+            if height < pops {
+                underflowed = true;
+                break;
+            }
+            height = (height - pops + pushes).min(HEIGHT_CAP);
+        }
+        if underflowed {
+            continue;
+        }
+        for exit in &blocks[b].exits {
+            if let Exit::Block(next) = *exit {
+                if entry[next].is_none_or(|h| h < height) {
+                    entry[next] = Some(height);
+                    work.push(next);
+                }
+            }
+        }
+    }
+
+    let mut underflows = vec![];
+    for (b, block) in blocks.iter().enumerate() {
+        let mut height = match entry[b] {
+            Some(height) if reachable[b] => height,
+            _ => continue,
+        };
+        for (i, op) in code.iter().enumerate().take(block.end).skip(block.start) {
+            let (pops, pushes) = op.stack_effect();
+            if height < pops {
+                underflows.push(i);
+                break;
+            }
+            height = (height - pops + pushes).min(HEIGHT_CAP);
+        }
+    }
+    underflows
+}
+
+// Longest path through the reachable blocks, or None if they loop. A
+// post-order walk with its own stack, since code can chain any number of
+// blocks.
+fn max_gas(code: &[Op], blocks: &[Block], schedule: &GasSchedule) -> Option<Gas> {
+    // 0 = unvisited, 1 = on the current path, 2 = done
+    let mut state = vec![0u8; blocks.len()];
+    let mut best: Vec<Gas> = vec![0; blocks.len()];
+    // each block on the path, with how many of its exits have been followed
+    let mut path = vec![(0, 0)];
+    state[0] = 1;
+    while let Some(&(b, followed)) = path.last() {
+        match blocks[b].exits.get(followed) {
+            Some(exit) => {
+                path.last_mut().unwrap().1 += 1;
+                if let Exit::Block(next) = *exit {
+                    match state[next] {
+                        0 => {
+                            state[next] = 1;
+                            path.push((next, 0));
+                        }
+                        1 => return None,
+                        _ => {}
+                    }
+                }
+            }
+            None => {
+                path.pop();
+                let own: Gas = code[blocks[b].start..blocks[b].end]
+                    .iter()
+                    .map(|op| schedule.cost(*op))
+                    .sum();
+                let tail = blocks[b]
+                    .exits
+                    .iter()
+                    .filter_map(|exit| match *exit {
+                        Exit::Block(next) => Some(best[next]),
+                        _ => None,
+                    })
+                    .max()
+                    .unwrap_or(0);
+                state[b] = 2;
+                best[b] = own + tail;
+            }
+        }
+    }
+    Some(best[0])
+}
+
+fn can_reach_stop(blocks: &[Block], doomed: &BTreeSet<usize>) -> bool {
+    let mut seen = vec![false; blocks.len()];
+    let mut work = vec![0];
+    seen[0] = true;
+    while let Some(b) = work.pop() {
+        if doomed.contains(&b) {
+            continue;
+        }
+        for exit in &blocks[b].exits {
+            match *exit {
+                Exit::Stop => return true,
+                Exit::Fail => {}
+                Exit::Block(next) => {
+                    if !seen[next] {
+                        seen[next] = true;
+                        work.push(next);
+                    }
+                }
+            }
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{super::asm::assemble, super::gasschedule::GasSchedule, super::ops::Op, analyze};
+
+    fn analyze_asm(source: &str) -> super::Analysis {
+        analyze(
+            &Op::from_bytes(&assemble(source).unwrap()),
+            &GasSchedule::default(),
+        )
+    }
+
+    #[test]
+    fn straight_line_code() {
+        let analysis = analyze_asm("PUSH1 2\nADDVAL\nSTOP\nPUSH1 1");
+        assert_eq!(analysis.unreachable, vec![3]);
+        assert!(analysis.stack_underflows.is_empty());
+        assert!(!analysis.falls_off_end);
+        let cost = Op::PUSH1(2).to_cost() + Op::ADDVAL.to_cost() + Op::STOP.to_cost();
+        assert_eq!(analysis.max_gas, Some(cost));
+        assert!(analysis.can_succeed);
+        assert!(analyze(&[], &GasSchedule::default()).can_succeed);
+
+        let analysis = analyze_asm("PUSH1 1\nPUSH1 0\nRETURN\nPUSH1 1");
+        assert_eq!(analysis.unreachable, vec![3]);
+        assert!(analysis.can_succeed);
+        assert_eq!(analyze_asm("RETURN").stack_underflows, vec![0]);
+    }
+
+    #[test]
+    fn underflow_on_every_path() {
+        let analysis = analyze_asm(
+            "
+            PUSH1 0
+            JUMPI skip
+            PUSH1 1
+        skip:
+            ADD
+            STOP
+            ",
+        );
+        // the fallthrough path pushes one item but ADD needs two
+        assert_eq!(analysis.stack_underflows, vec![4]);
+        assert!(!analysis.can_succeed);
+
+        // one path has enough items, so nothing is guaranteed
+        let analysis = analyze_asm(
+            "
+            PUSH1 0
+            JUMPI skip
+            PUSH1 1
+        skip:
+            POP
+            STOP
+            ",
+        );
+        assert!(analysis.stack_underflows.is_empty());
+        assert!(analysis.can_succeed);
+    }
+
+    #[test]
+    fn invalid_jumps_and_ends() {
+        let analysis = analyze_asm("PUSH1 1\nPUSH1 200\nJUMPI\nSTOP");
+        assert_eq!(analysis.invalid_jumps, vec![2]);
+        assert!(analysis.can_succeed);
+
+        let analysis = analyze_asm("PUSH1 200\nJUMP\nSTOP");
+        assert_eq!(analysis.invalid_jumps, vec![1]);
+        assert_eq!(analysis.unreachable, vec![2]);
+        assert!(!analysis.can_succeed);
+
+        let analysis = analyze_asm("PUSH1 1\nADDVAL");
+        assert!(analysis.falls_off_end);
+        assert!(!analysis.can_succeed);
+
+        let analysis = analyze_asm("PUSH1 1\nINVALID(0x05)");
+        assert_eq!(analysis.invalid_ops, vec![1]);
+        assert!(!analysis.can_succeed);
+    }
+
+    #[test]
+    fn loops_and_dynamic_jumps() {
+        let analysis = analyze_asm("loop:\nPUSH1 1\nADDVAL\nJUMP loop");
+        assert_eq!(analysis.max_gas, None);
+        assert!(!analysis.dynamic_jumps);
+        // an infinite loop can only run out of gas
+        assert!(!analysis.can_succeed);
+
+        let analysis = analyze_asm("PUSH1 1\nPUSH1 1\nADD\nJUMP\nPOP\nSTOP");
+        assert!(analysis.dynamic_jumps);
+        assert!(analysis.unreachable.is_empty());
+        assert!(analysis.can_succeed);
+    }
+
+    #[test]
+    fn max_gas_takes_the_longest_branch() {
+        let ops = Op::from_bytes(
+            &assemble(
+                "
+                PUSH1 1
+                JUMPI cheap
+                PUSH1 1
+                ADDVAL
+                PUSH1 1
+                ADDVAL
+            cheap:
+                STOP
+                ",
+            )
+            .unwrap(),
+        );
+        let analysis = analyze(&ops, &GasSchedule::default());
+        let total = ops.iter().fold(0, |sum, op| sum + op.to_cost());
+        assert_eq!(analysis.max_gas, Some(total));
+
+        // the bound follows the schedule, not the default costs
+        let schedule = GasSchedule::default().with_cost(Op::ADDVAL, 100);
+        let analysis = analyze(&ops, &schedule);
+        let total = ops.iter().fold(0, |sum, op| sum + schedule.cost(*op));
+        assert_eq!(analysis.max_gas, Some(total));
+        assert_eq!(
+            total,
+            ops.iter().fold(0, |sum, op| sum + op.to_cost()) + 2 * (100 - Op::ADDVAL.to_cost())
+        );
+    }
+
+    #[test]
+    fn long_chains_of_blocks() {
+        // jump over a STOP, then 50,000 JUMPIs that either go back to it or
+        // fall through to the next
+        let chain = 50_000;
+        let mut code = vec![0x60, 3, 0x56, 0x00];
+        for _ in 0..chain {
+            code.extend(&[0x60, 0, 0x60, 2, 0x57]);
+        }
+        code.push(0x00);
+        let ops = Op::from_bytes(&code);
+        let analysis = analyze(&ops, &GasSchedule::default());
+        // every op is on the longest path, except one of the two STOPs
+        let total = ops.iter().fold(0, |sum, op| sum + op.to_cost());
+        assert_eq!(analysis.max_gas, Some(total - Op::STOP.to_cost()));
+        assert!(analysis.can_succeed);
+    }
+}
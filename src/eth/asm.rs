@@ -0,0 +1,352 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use super::ops::Op;
+
+/// One parsed assembly item. Labels resolve to op indices, since that's
+/// what JUMP and JUMPI take as their destination.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum AsmItem {
+    Op(Op),
+    Label(String),
+    PushLabel(String),
+    // a PUSH1 with no operand; only legal as the final item
+    TruncatedPush,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct AsmError {
+    // None for errors found while resolving labels
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl AsmError {
+    fn new(line: usize, message: String) -> AsmError {
+        AsmError {
+            line: Some(line),
+            message,
+        }
+    }
+
+    fn unlocated(message: String) -> AsmError {
+        AsmError {
+            line: None,
+            message,
+        }
+    }
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "line {}: {}", line, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+/// Assembles mnemonic text into bytecode. Accepts the disassembler's
+/// output, so `assemble(&disassemble(code))` gives back `code`.
+///
+/// ```text
+/// loop:            ; labels name the op that follows
+///     PUSH1 1
+///     ADDVAL
+///     JUMP loop    ; shorthand for PUSH1 loop, JUMP
+/// ```
+pub fn assemble(source: &str) -> Result<Vec<u8>, AsmError> {
+    assemble_items(&parse(source)?)
+}
+
+pub fn parse(source: &str) -> Result<Vec<AsmItem>, AsmError> {
+    let mut items = vec![];
+    // the byte offset of the next instruction
+    let mut offset = 0;
+    for (number, line) in source.lines().enumerate() {
+        let start = items.len();
+        parse_line(line, number + 1, offset, &mut items)?;
+        offset += items[start..].iter().map(item_size).sum::<usize>();
+    }
+    Ok(items)
+}
+
+/// Resolves labels and encodes the items.
+pub fn assemble_items(items: &[AsmItem]) -> Result<Vec<u8>, AsmError> {
+    let labels = resolve_labels(items)?;
+    let mut bytes = vec![];
+    for (i, item) in items.iter().enumerate() {
+        match item {
+            AsmItem::Op(op) => bytes.extend(Op::to_bytes(&[*op])),
+            AsmItem::Label(_) => {}
+            AsmItem::PushLabel(name) => match labels.get(name) {
+                Some(index) => bytes.extend(&[Op::PUSH1(0).to_byte(), *index]),
+                None => return Err(AsmError::unlocated(format!("undefined label '{}'", name))),
+            },
+            AsmItem::TruncatedPush => {
+                if items[i + 1..]
+                    .iter()
+                    .any(|item| !matches!(item, AsmItem::Label(_)))
+                {
+                    return Err(AsmError::unlocated(
+                        "PUSH1 without an operand must be the last instruction".to_string(),
+                    ));
+                }
+                bytes.push(Op::PUSH1(0).to_byte());
+            }
+        }
+    }
+    Ok(bytes)
+}
+
+fn resolve_labels(items: &[AsmItem]) -> Result<HashMap<String, u8>, AsmError> {
+    let mut labels = HashMap::new();
+    let mut index = 0usize;
+    for item in items {
+        match item {
+            AsmItem::Label(name) => {
+                if index > usize::from(u8::MAX) {
+                    return Err(AsmError::unlocated(format!(
+                        "label '{}' is past op 255 and can't be jumped to",
+                        name
+                    )));
+                }
+                if labels.insert(name.clone(), index as u8).is_some() {
+                    return Err(AsmError::unlocated(format!("duplicate label '{}'", name)));
+                }
+            }
+            _ => index += 1,
+        }
+    }
+    Ok(labels)
+}
+
+fn item_size(item: &AsmItem) -> usize {
+    match item {
+        AsmItem::Op(op) => Op::to_bytes(&[*op]).len(),
+        AsmItem::Label(_) => 0,
+        AsmItem::PushLabel(_) => 2,
+        AsmItem::TruncatedPush => 1,
+    }
+}
+
+fn parse_line(
+    line: &str,
+    number: usize,
+    offset: usize,
+    items: &mut Vec<AsmItem>,
+) -> Result<(), AsmError> {
+    let line = match line.find(';') {
+        Some(pos) => &line[..pos],
+        None => line,
+    };
+    let line = match line.find("//") {
+        Some(pos) => &line[..pos],
+        None => line,
+    };
+    let mut tokens = line.split_whitespace().peekable();
+
+    // the disassembler starts each line with the instruction's offset
+    let offset_column = format!("{:04x}:", offset);
+    let mut after_offset = line.split_whitespace().skip(1);
+    if tokens.peek() == Some(&offset_column.as_str())
+        && after_offset
+            .next()
+            .is_some_and(|token| !token.ends_with(':'))
+    {
+        tokens.next();
+    }
+
+    // leading labels
+    while let Some(token) = tokens.peek().cloned() {
+        if !token.ends_with(':') {
+            break;
+        }
+        tokens.next();
+        let name = &token[..token.len() - 1];
+        if is_offset(name) {
+            return Err(AsmError::new(
+                number,
+                format!("label '{}' reads as a disassembler offset", name),
+            ));
+        }
+        if !is_label(name) {
+            return Err(AsmError::new(number, format!("invalid label '{}'", name)));
+        }
+        items.push(AsmItem::Label(name.to_string()));
+    }
+
+    let mnemonic = match tokens.next() {
+        Some(mnemonic) => mnemonic.to_uppercase(),
+        None => return Ok(()),
+    };
+    let operand = tokens.next();
+    if let Some(extra) = tokens.next() {
+        return Err(AsmError::new(number, format!("unexpected '{}'", extra)));
+    }
+
+    match (mnemonic.as_str(), operand) {
+        ("PUSH1", None) => items.push(AsmItem::TruncatedPush),
+        ("PUSH1", Some(operand)) => items.push(parse_push_operand(operand, number)?),
+        ("JUMP", Some(label)) | ("JUMPI", Some(label)) => {
+            items.push(parse_push_operand(label, number)?);
+            items.push(AsmItem::Op(if mnemonic == "JUMP" {
+                Op::JUMP
+            } else {
+                Op::JUMPI
+            }));
+        }
+        (_, None) if mnemonic.starts_with("INVALID(") && mnemonic.ends_with(')') => {
+            let byte = parse_byte(&mnemonic[8..mnemonic.len() - 1], number)?;
+            match Op::from_byte(byte) {
+                Op::INVALID(_) => items.push(AsmItem::Op(Op::INVALID(byte))),
+                op => {
+                    return Err(AsmError::new(
+                        number,
+                        format!("0x{:02x} is {}, not an invalid opcode", byte, op.mnemonic()),
+                    ))
+                }
+            }
+        }
+        (_, None) => match Op::from_mnemonic(&mnemonic) {
+            Some(op) => items.push(AsmItem::Op(op)),
+            None => {
+                return Err(AsmError::new(
+                    number,
+                    format!("unknown mnemonic '{}'", mnemonic),
+                ))
+            }
+        },
+        (_, Some(operand)) => {
+            return Err(AsmError::new(
+                number,
+                format!("{} doesn't take an operand, got '{}'", mnemonic, operand),
+            ))
+        }
+    }
+    Ok(())
+}
+
+fn parse_push_operand(operand: &str, number: usize) -> Result<AsmItem, AsmError> {
+    if is_label(operand) {
+        Ok(AsmItem::PushLabel(operand.to_string()))
+    } else {
+        Ok(AsmItem::Op(Op::PUSH1(parse_byte(operand, number)?)))
+    }
+}
+
+fn parse_byte(text: &str, number: usize) -> Result<u8, AsmError> {
+    let parsed = match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => u8::from_str_radix(hex, 16),
+        None => text.parse(),
+    };
+    parsed.map_err(|_| AsmError::new(number, format!("'{}' isn't a byte", text)))
+}
+
+// the disassembler prints offsets as at least four hex digits, so names
+// like that are kept for them
+fn is_offset(name: &str) -> bool {
+    name.len() >= 4 && name.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+fn is_label(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {}
+        _ => return false,
+    }
+    chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng;
+
+    use super::{super::disasm::disassemble, assemble, AsmError};
+
+    #[test]
+    fn test_assemble_value_ops() {
+        assert_eq!(
+            assemble("PUSH1 2\nADDVAL\nSTOP"),
+            Ok(vec![0x60, 2, 0xb1, 0x00])
+        );
+        assert_eq!(
+            assemble("push1 0x06 ; comment\nsetval // another\nPUSH1 1\nSUBVAL"),
+            Ok(vec![0x60, 6, 0xb0, 0x60, 1, 0xb2])
+        );
+    }
+
+    #[test]
+    fn test_labels() {
+        let source = "
+            PUSH1 3
+        loop:
+            PUSH1 1
+            ADDVAL
+            PUSH1 1
+            JUMPI end
+            JUMP loop
+        end: STOP
+        ";
+        assert_eq!(
+            assemble(source),
+            Ok(vec![
+                0x60, 3, 0x60, 1, 0xb1, 0x60, 1, 0x60, 8, 0x57, 0x60, 1, 0x56, 0x00
+            ])
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            assemble("STOP\nFROB"),
+            Err(AsmError {
+                line: Some(2),
+                message: "unknown mnemonic 'FROB'".to_string()
+            })
+        );
+        assert!(assemble("PUSH1 256").is_err());
+        assert!(assemble("JUMP nowhere").is_err());
+        assert!(assemble("a:\na:\nSTOP").is_err());
+        assert!(assemble("INVALID(0x60)").is_err());
+        assert!(assemble("PUSH1\nSTOP").is_err());
+        assert!(assemble("ADD 4").is_err());
+
+        // names the disassembler could have printed as an offset
+        for label in &["dead", "beef", "face"] {
+            let source = format!("JUMP {}\n{}: STOP", label, label);
+            assert_eq!(
+                assemble(&source),
+                Err(AsmError {
+                    line: Some(2),
+                    message: format!("label '{}' reads as a disassembler offset", label)
+                })
+            );
+        }
+        assert_eq!(
+            assemble("JUMP done\ndone: STOP"),
+            Ok(vec![0x60, 2, 0x56, 0x00])
+        );
+        // an offset column that doesn't match where the instruction lands
+        assert!(assemble("0003: STOP").is_err());
+        assert!(assemble("0000:").is_err());
+    }
+
+    #[test]
+    fn test_disassembly_round_trip() {
+        let code = vec![0x60, 100, 0x60, 4, 0x57, 0x05, 0x00, 0xa3, 0x60];
+        assert_eq!(assemble(&disassemble(&code)), Ok(code));
+
+        let mut rng = rand::thread_rng();
+        for _ in 0..200 {
+            let len = rng.gen_range(0, 64);
+            let code: Vec<u8> = (0..len).map(|_| rng.gen()).collect();
+            assert_eq!(assemble(&disassemble(&code)), Ok(code));
+        }
+
+        // offsets from a000 on are made of letters, and still aren't labels
+        let code: Vec<u8> = (0..0xa100).map(|i| (i % 0x50) as u8).collect();
+        assert!(disassemble(&code).contains("\na000: "));
+        assert_eq!(assemble(&disassemble(&code)), Ok(code));
+    }
+}
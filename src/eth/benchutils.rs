@@ -49,16 +49,26 @@ impl<'a> TxnGenerator<'a> {
         // 1 -> adds 2 to value
         // 2 -> sets value to 6
 
-        let codes = vec![
-            // empty code
-            vec![],
-            // [Op::PUSH1(2), Op::ADDVAL, Op::STOP];
-            vec![0x60, 2, 0xb1, 0x00],
-            // [PUSH1(2), PUSH1(3), PUSH1(4), PUSH1(7), PUSH1(1), ADD, SUB, MUL, DIV, SETVAL, STOP]
-            vec![
-                0x60, 2, 0x60, 3, 0x60, 4, 0x60, 7, 0x60, 1, 0x01, 0x03, 0x02, 0x04, 0xb0, 0x00,
-            ],
-        ];
+        let codes: Vec<Vec<u8>> = [
+            "",
+            "PUSH1 2
+             ADDVAL
+             STOP",
+            "PUSH1 2
+             PUSH1 3
+             PUSH1 4
+             PUSH1 7
+             PUSH1 1
+             ADD
+             SUB
+             MUL
+             DIV
+             SETVAL
+             STOP",
+        ]
+        .iter()
+        .map(|source| asm::assemble(source).unwrap())
+        .collect();
 
         TxnGenerator {
             lastindex: 0,
@@ -68,31 +78,43 @@ impl<'a> TxnGenerator<'a> {
                 // from actor 0 to actor 1
                 ETHTxn {
                     nonce: 0,
+                    chain_id: ethtxn::DEFAULT_CHAIN_ID,
                     gasprice: Wei::from_wei(2),
+                    max_fee: None,
+                    max_priority_fee: None,
                     gaslimit: 10,
-                    recipient: actors[1].address.clone(),
+                    recipient: Some(actors[1].address.clone()),
                     value: Wei::from_wei(100),
                     code: codes[0].clone(),
+                    encoding: HashEncoding::Bincode,
                     ecdsa_fields: ethtxn::utils::get_bs_ecsda_field(&actors[0].secretkey.clone()),
                 },
                 // from actor 1 to actor 2
                 ETHTxn {
                     nonce: 0,
+                    chain_id: ethtxn::DEFAULT_CHAIN_ID,
                     gasprice: Wei::from_wei(1),
+                    max_fee: None,
+                    max_priority_fee: None,
                     gaslimit: 10,
-                    recipient: actors[2].address.clone(),
+                    recipient: Some(actors[2].address.clone()),
                     value: Wei::from_wei(76), // incremented to 78 by code
                     code: codes[1].clone(),
+                    encoding: HashEncoding::Bincode,
                     ecdsa_fields: ethtxn::utils::get_bs_ecsda_field(&actors[1].secretkey.clone()),
                 },
                 // from actor 2 to actor 0
                 ETHTxn {
                     nonce: 0,
+                    chain_id: ethtxn::DEFAULT_CHAIN_ID,
                     gasprice: Wei::from_wei(1),
+                    max_fee: None,
+                    max_priority_fee: None,
                     gaslimit: 30,
-                    recipient: actors[0].address.clone(),
+                    recipient: Some(actors[0].address.clone()),
                     value: Wei::from_wei(0), // set to 6 by code
                     code: codes[2].clone(),
+                    encoding: HashEncoding::Bincode,
                     ecdsa_fields: ethtxn::utils::get_bs_ecsda_field(&actors[2].secretkey.clone()),
                 },
             ],
@@ -202,6 +224,16 @@ pub fn generate_blockchain(actors: &Actors) -> ETHBlockchain {
     chain
 }
 
+// a state where every actor can afford all TOTAL_TXNS transactions, mined by
+// someone who never sends any
+pub fn generate_evmstate(actors: &Actors) -> EVMState {
+    let mut state = ETHState::default();
+    for actor in actors {
+        state.pay(&actor.address, Wei::from_wei(1_000_000));
+    }
+    EVMState::new(state, [0; 20])
+}
+
 pub fn run_benchmarks() {
     let actors = generate_actors();
     let mut blockchain = generate_blockchain(&actors);
@@ -0,0 +1,279 @@
+// Optimistic parallel execution in the style of Block-STM: transactions run
+// speculatively against the state before them, and one is run again when an
+// earlier transaction turns out to have written an account it read.
+
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+
+use super::aliases::ETHAddress;
+use super::ethaccount::ETHAccount;
+use super::ethstate::{ETHState, StateAccess};
+use super::parallel;
+use super::wei::Wei;
+
+/// A view of an `ETHState` that records which accounts a transaction read
+/// and what it wrote, without modifying the state underneath. Payments to
+/// an account that hasn't been read are kept as deltas, so transactions that
+/// only pay the same account (such as the miner) don't conflict.
+pub struct SpeculativeState<'a> {
+    base: &'a ETHState,
+    reads: RefCell<HashSet<ETHAddress>>,
+    writes: HashMap<ETHAddress, ETHAccount>,
+    deltas: HashMap<ETHAddress, Wei>,
+}
+
+// a finished speculative run, waiting for everything before it to commit
+struct Speculation<R> {
+    result: R,
+    reads: HashSet<ETHAddress>,
+    writes: HashMap<ETHAddress, ETHAccount>,
+    deltas: HashMap<ETHAddress, Wei>,
+}
+
+impl<'a> SpeculativeState<'a> {
+    pub fn new(base: &'a ETHState) -> SpeculativeState<'a> {
+        SpeculativeState {
+            base,
+            reads: RefCell::new(HashSet::new()),
+            writes: HashMap::new(),
+            deltas: HashMap::new(),
+        }
+    }
+
+    // the account as this transaction sees it, its own payments included
+    fn account(&self, addr: &ETHAddress) -> Option<Cow<'_, ETHAccount>> {
+        if let Some(account) = self.writes.get(addr) {
+            return Some(Cow::Borrowed(account));
+        }
+        self.reads.borrow_mut().insert(*addr);
+        match (self.base.get_account(addr), self.deltas.get(addr)) {
+            (account, None) => account.map(Cow::Borrowed),
+            (account, Some(&delta)) => {
+                let mut account = account.cloned().unwrap_or_else(|| ETHAccount::new(*addr));
+                account.balance += delta;
+                Some(Cow::Owned(account))
+            }
+        }
+    }
+
+    fn account_mut(&mut self, addr: &ETHAddress) -> Option<&mut ETHAccount> {
+        if !self.writes.contains_key(addr) {
+            let account = self.account(addr)?.into_owned();
+            self.deltas.remove(addr);
+            self.writes.insert(*addr, account);
+        }
+        self.writes.get_mut(addr)
+    }
+
+    fn finish<R>(self, result: R) -> Speculation<R> {
+        Speculation {
+            result,
+            reads: self.reads.into_inner(),
+            writes: self.writes,
+            deltas: self.deltas,
+        }
+    }
+}
+
+impl<'a> StateAccess for SpeculativeState<'a> {
+    fn key_exists(&self, addr: &ETHAddress) -> bool {
+        self.account(addr).is_some()
+    }
+
+    fn get_value(&self, addr: &ETHAddress) -> Option<Wei> {
+        self.account(addr).map(|account| account.balance)
+    }
+
+    fn get_nonce(&self, addr: &ETHAddress) -> Option<u32> {
+        self.account(addr).map(|account| account.nonce)
+    }
+
+    fn increment_nonce(&mut self, addr: &ETHAddress) {
+        self.account_mut(addr).unwrap().nonce += 1;
+    }
+
+    fn safe_deduct(&mut self, addr: &ETHAddress, amount: Wei) -> bool {
+        match self.account_mut(addr) {
+            Some(account) => match account.balance - amount {
+                Some(val) => {
+                    account.balance = val;
+                    true
+                }
+                None => false,
+            },
+            None => false,
+        }
+    }
+
+    fn pay(&mut self, addr: &ETHAddress, amount: Wei) {
+        match self.writes.get_mut(addr) {
+            Some(account) => account.balance += amount,
+            None => *self.deltas.entry(*addr).or_default() += amount,
+        }
+    }
+
+    fn get_code(&self, addr: &ETHAddress) -> Option<&[u8]> {
+        if let Some(account) = self.writes.get(addr) {
+            return Some(&account.code);
+        }
+        self.reads.borrow_mut().insert(*addr);
+        match self.base.get_account(addr) {
+            Some(account) => Some(&account.code),
+            // paid, but not yet in the base state
+            None if self.deltas.contains_key(addr) => Some(&[]),
+            None => None,
+        }
+    }
+
+    fn set_code(&mut self, addr: &ETHAddress, code: Vec<u8>) {
+        if self.account_mut(addr).is_none() {
+            self.writes.insert(*addr, ETHAccount::new(*addr));
+        }
+        self.writes.get_mut(addr).unwrap().code = code;
+    }
+}
+
+impl<R> Speculation<R> {
+    // applies the run to `state`, returning every account it changed
+    fn commit(self, state: &mut ETHState) -> (R, HashSet<ETHAddress>) {
+        let mut written = HashSet::new();
+        for (addr, account) in self.writes {
+            written.insert(addr);
+            state.set_account(account);
+        }
+        for (addr, delta) in self.deltas {
+            written.insert(addr);
+            state.pay(&addr, delta);
+        }
+        (self.result, written)
+    }
+}
+
+/// Runs `count` transactions as if one after another, `run(i, view)` running
+/// the i-th against a view of the state before it. Runs are spread across
+/// `num_threads` threads; results are committed to `state` in order, and a
+/// run whose reads an earlier commit changed is thrown away and repeated.
+/// `run` must only depend on what it reads through the view.
+pub fn execute<R, F>(state: &mut ETHState, count: usize, num_threads: usize, run: F) -> Vec<R>
+where
+    R: Send,
+    F: Fn(usize, &mut SpeculativeState) -> R + Sync,
+{
+    let mut speculations: Vec<Option<Speculation<R>>> = (0..count).map(|_| None).collect();
+    let mut results = Vec::with_capacity(count);
+    while results.len() < count {
+        // the first pending transaction sees every commit before it, so each
+        // round commits at least one
+        let pending: Vec<usize> = (results.len()..count)
+            .filter(|&index| speculations[index].is_none())
+            .collect();
+        for (index, speculation) in speculate(state, &pending, num_threads, &run) {
+            speculations[index] = Some(speculation);
+        }
+        while let Some(speculation) = speculations.get_mut(results.len()).and_then(Option::take) {
+            let (result, written) = speculation.commit(state);
+            results.push(result);
+            for later in speculations[results.len()..].iter_mut() {
+                if let Some(ref speculation) = *later {
+                    if !speculation.reads.is_disjoint(&written) {
+                        *later = None;
+                    }
+                }
+            }
+        }
+    }
+    results
+}
+
+// runs the `pending` transactions against `state` across `num_threads` threads
+fn speculate<R, F>(
+    state: &ETHState,
+    pending: &[usize],
+    num_threads: usize,
+    run: &F,
+) -> Vec<(usize, Speculation<R>)>
+where
+    R: Send,
+    F: Fn(usize, &mut SpeculativeState) -> R + Sync,
+{
+    parallel::map_chunks(pending, num_threads, |&index| {
+        let mut view = SpeculativeState::new(state);
+        let result = run(index, &mut view);
+        (index, view.finish(result))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::{
+        super::aliases::ETHAddress, super::ethstate::ETHState, super::ethstate::StateAccess,
+        super::wei::Wei, execute,
+    };
+
+    // moves `amount` if `from` has it, paying the miner a fee either way
+    fn transfer<S: StateAccess>(state: &mut S, &(from, to, amount): &(u8, u8, u128)) -> bool {
+        state.pay(&[0; 20], Wei::from_wei(1));
+        let moved = state.safe_deduct(&[from; 20], Wei::from_wei(amount));
+        if moved {
+            state.pay(&[to; 20], Wei::from_wei(amount));
+        }
+        moved
+    }
+
+    fn funded(accounts: &[u8]) -> ETHState {
+        let mut state = ETHState::default();
+        for &account in accounts {
+            let addr: ETHAddress = [account; 20];
+            state.pay(&addr, Wei::from_wei(10));
+        }
+        state
+    }
+
+    // runs `transfers` both ways, returning how many runs the parallel
+    // execution took
+    fn check_matches_sequential(transfers: &[(u8, u8, u128)], num_threads: usize) -> usize {
+        let accounts: Vec<u8> = (1..=8).collect();
+        let mut sequential = funded(&accounts);
+        let expected: Vec<bool> = transfers
+            .iter()
+            .map(|spec| transfer(&mut sequential, spec))
+            .collect();
+
+        let runs = AtomicUsize::new(0);
+        let mut parallel = funded(&accounts);
+        let results = execute(&mut parallel, transfers.len(), num_threads, |i, view| {
+            runs.fetch_add(1, Ordering::SeqCst);
+            transfer(view, &transfers[i])
+        });
+        assert_eq!(results, expected);
+        assert_eq!(parallel, sequential);
+        runs.into_inner()
+    }
+
+    #[test]
+    fn disjoint_transfers_run_once() {
+        let transfers = [(1, 2, 5), (3, 4, 5), (5, 6, 5), (7, 8, 5)];
+        for num_threads in 1..=4 {
+            assert_eq!(check_matches_sequential(&transfers, num_threads), 4);
+        }
+    }
+
+    #[test]
+    fn conflicting_transfers_run_again() {
+        // each transfer spends what the one before it received, so only the
+        // first can succeed on its first run
+        let transfers = [(1, 2, 10), (2, 3, 20), (3, 4, 30), (4, 5, 40), (1, 6, 1)];
+        for num_threads in 1..=5 {
+            let runs = check_matches_sequential(&transfers, num_threads);
+            assert!(runs > transfers.len(), "{} runs", runs);
+        }
+        // paying an account that's later read, and a new account
+        let transfers = [(1, 9, 3), (9, 1, 2), (2, 1, 20), (1, 2, 25), (0, 3, 1)];
+        for num_threads in 1..=5 {
+            check_matches_sequential(&transfers, num_threads);
+        }
+    }
+}
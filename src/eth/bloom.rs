@@ -0,0 +1,156 @@
+use serde::{Serialize, Serializer};
+use sha3::Digest;
+use std::fmt;
+
+use super::aliases::ETHAddress;
+use super::ethreceipt::ETHLog;
+
+pub const BLOOM_BYTES: usize = 256;
+
+/// A 2048-bit bloom filter over log addresses and topics, built with the
+/// yellow paper's M3:2048 function.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct Bloom {
+    bits: [u8; BLOOM_BYTES],
+}
+
+impl Bloom {
+    pub fn new() -> Bloom {
+        Bloom {
+            bits: [0; BLOOM_BYTES],
+        }
+    }
+
+    pub fn from_logs(logs: &[ETHLog]) -> Bloom {
+        let mut bloom = Bloom::new();
+        for log in logs {
+            bloom.accrue_log(log);
+        }
+        bloom
+    }
+
+    /// Sets the three bits selected by the low 11 bits of the first three
+    /// byte pairs of keccak(input).
+    pub fn accrue(&mut self, input: &[u8]) {
+        let hash = sha3::Keccak256::digest(input);
+        for i in 0..3 {
+            let (byte, mask) = Self::bit_position(hash[2 * i], hash[2 * i + 1]);
+            self.bits[byte] |= mask;
+        }
+    }
+
+    pub fn accrue_log(&mut self, log: &ETHLog) {
+        self.accrue(&log.address);
+        for topic in &log.topics {
+            self.accrue(&[*topic]);
+        }
+    }
+
+    pub fn accrue_bloom(&mut self, other: &Bloom) {
+        for (byte, other_byte) in self.bits.iter_mut().zip(other.bits.iter()) {
+            *byte |= *other_byte;
+        }
+    }
+
+    /// Returns false if the input was definitely never accrued.
+    pub fn contains_input(&self, input: &[u8]) -> bool {
+        let mut probe = Bloom::new();
+        probe.accrue(input);
+        self.contains_bloom(&probe)
+    }
+
+    pub fn contains_address(&self, addr: &ETHAddress) -> bool {
+        self.contains_input(addr)
+    }
+
+    pub fn contains_topic(&self, topic: u8) -> bool {
+        self.contains_input(&[topic])
+    }
+
+    pub fn contains_bloom(&self, other: &Bloom) -> bool {
+        self.bits
+            .iter()
+            .zip(other.bits.iter())
+            .all(|(byte, other_byte)| byte & other_byte == *other_byte)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bits.iter().all(|byte| *byte == 0)
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bits
+    }
+
+    // bit 0 is the least significant bit of the last byte
+    fn bit_position(high: u8, low: u8) -> (usize, u8) {
+        let bit = ((usize::from(high) << 8) | usize::from(low)) % (BLOOM_BYTES * 8);
+        (BLOOM_BYTES - 1 - bit / 8, 1 << (bit % 8))
+    }
+}
+
+impl Default for Bloom {
+    fn default() -> Bloom {
+        Bloom::new()
+    }
+}
+
+// one hex string reads better than 256 comma-separated numbers
+impl fmt::Debug for Bloom {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Bloom(")?;
+        for byte in self.bits.iter() {
+            write!(f, "{:02x}", byte)?;
+        }
+        write!(f, ")")
+    }
+}
+
+// serde only derives for arrays of up to 32 elements
+impl Serialize for Bloom {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(&self.bits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{super::ethreceipt::ETHLog, Bloom};
+
+    #[test]
+    fn test_bloom_sets_three_bits() {
+        let mut bloom = Bloom::new();
+        assert!(bloom.is_empty());
+        bloom.accrue(&[0xab; 20]);
+        let bits_set: u32 = bloom.as_bytes().iter().map(|b| b.count_ones()).sum();
+        assert!((1..=3).contains(&bits_set));
+        assert!(bloom.contains_input(&[0xab; 20]));
+    }
+
+    #[test]
+    fn test_bloom_logs() {
+        let logs = vec![
+            ETHLog {
+                address: [1; 20],
+                topics: vec![7, 9],
+                data: 0,
+            },
+            ETHLog {
+                address: [2; 20],
+                topics: vec![],
+                data: 3,
+            },
+        ];
+        let bloom = Bloom::from_logs(&logs);
+        assert!(bloom.contains_address(&[1; 20]));
+        assert!(bloom.contains_address(&[2; 20]));
+        assert!(bloom.contains_topic(7));
+        assert!(bloom.contains_topic(9));
+        assert!(!bloom.contains_address(&[3; 20]));
+
+        let mut combined = Bloom::new();
+        combined.accrue_bloom(&Bloom::from_logs(&logs[..1]));
+        combined.accrue_bloom(&Bloom::from_logs(&logs[1..]));
+        assert_eq!(combined, bloom);
+    }
+}
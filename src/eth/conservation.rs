@@ -0,0 +1,293 @@
+// Property tests for how transactions move wei and nonces around.
+
+use proptest::prelude::*;
+use proptest::sample::select;
+
+use super::aliases::ETHAddress;
+use super::ethstate::ETHState;
+use super::ethtxn::utils::get_bs_ecsda_field;
+use super::ethtxn::{ETHTxn, DEFAULT_CHAIN_ID};
+use super::evmstate::{EVMState, FailureReason, MINING_REWARD};
+use super::gas::Gas;
+use super::ops::Op;
+use super::rlp::HashEncoding;
+use super::wei::Wei;
+
+const ACCOUNTS: usize = 4;
+
+// real ops only, so generated code gets somewhere before failing
+const OP_BYTES: &[u8] = &[
+    0x00, 0x01, 0x02, 0x03, 0x04, 0x10, 0x11, 0x14, 0x15, 0x50, 0x51, 0x52, 0x56, 0x57, 0x60, 0xa0,
+    0xa1, 0xb0, 0xb1, 0xb2, 0xf3,
+];
+
+#[derive(Debug, Clone)]
+enum Action {
+    Send(TxnSpec),
+    RewardMiner,
+}
+
+#[derive(Debug, Clone)]
+struct TxnSpec {
+    sender: usize,
+    // None creates a contract
+    recipient: Option<usize>,
+    gasprice: u128,
+    // fee market caps, which leave gasprice unused when both are set
+    max_fee: Option<u128>,
+    max_priority_fee: Option<u128>,
+    gaslimit: Gas,
+    value: u128,
+    code: Vec<u8>,
+    // reuse the nonce of the sender's last transaction
+    stale_nonce: bool,
+    // signed by a key no account belongs to
+    forged: bool,
+}
+
+fn secret_key(index: usize) -> secp256k1::SecretKey {
+    secp256k1::SecretKey::parse(&[index as u8 + 1; 32]).unwrap()
+}
+
+fn address(secret: &secp256k1::SecretKey) -> ETHAddress {
+    ETHTxn::get_address_from_public_key(&secp256k1::PublicKey::from_secret_key(secret)).unwrap()
+}
+
+fn code() -> impl Strategy<Value = Vec<u8>> {
+    prop::collection::vec(prop_oneof![select(OP_BYTES), 0..8u8], 0..24)
+}
+
+fn txn_spec() -> impl Strategy<Value = TxnSpec> {
+    (
+        0..ACCOUNTS,
+        prop::option::weighted(0.9, 0..ACCOUNTS),
+        0..5u128,
+        prop::option::of(0..8u128),
+        prop::option::of(0..4u128),
+        0..300u32,
+        0..60u128,
+        code(),
+        prop::bool::weighted(0.1),
+        prop::bool::weighted(0.1),
+    )
+        .prop_map(
+            |(
+                sender,
+                recipient,
+                gasprice,
+                max_fee,
+                max_priority_fee,
+                gaslimit,
+                value,
+                code,
+                stale_nonce,
+                forged,
+            )| TxnSpec {
+                sender,
+                recipient,
+                gasprice,
+                max_fee,
+                max_priority_fee,
+                gaslimit: Gas::from(gaslimit),
+                value,
+                code,
+                stale_nonce,
+                forged,
+            },
+        )
+}
+
+fn action() -> impl Strategy<Value = Action> {
+    prop_oneof![
+        8 => txn_spec().prop_map(Action::Send),
+        1 => Just(Action::RewardMiner),
+    ]
+}
+
+struct World {
+    addresses: Vec<ETHAddress>,
+    secrets: Vec<secp256k1::SecretKey>,
+    evm_state: EVMState,
+}
+
+impl World {
+    // account 0 mines, so it both earns fees and pays them
+    fn new(balances: &[u128], base_fee: u128) -> World {
+        let secrets: Vec<_> = (0..ACCOUNTS).map(secret_key).collect();
+        let addresses: Vec<_> = secrets.iter().map(address).collect();
+        let mut state = ETHState::default();
+        for (addr, &balance) in addresses.iter().zip(balances) {
+            state.pay(addr, Wei::from_wei(balance));
+        }
+        let mut evm_state = EVMState::new(state, addresses[0]);
+        evm_state.set_base_fee(Wei::from_wei(base_fee));
+        World {
+            evm_state,
+            addresses,
+            secrets,
+        }
+    }
+
+    fn state(&self) -> &ETHState {
+        self.evm_state.get_world_state()
+    }
+
+    fn nonces(&self) -> Vec<u32> {
+        self.addresses
+            .iter()
+            .map(|addr| self.state().get_nonce(addr).unwrap())
+            .collect()
+    }
+
+    fn build_txn(&self, spec: &TxnSpec) -> ETHTxn {
+        self.build_txn_after(spec, self.nonces()[spec.sender])
+    }
+
+    // a block's transactions, numbered as if each before it were accepted
+    fn build_block(&self, specs: &[TxnSpec]) -> Vec<ETHTxn> {
+        let mut nonces = self.nonces();
+        specs
+            .iter()
+            .map(|spec| {
+                let txn = self.build_txn_after(spec, nonces[spec.sender]);
+                nonces[spec.sender] = txn.nonce;
+                txn
+            })
+            .collect()
+    }
+
+    fn build_txn_after(&self, spec: &TxnSpec, nonce: u32) -> ETHTxn {
+        let mut txn = ETHTxn {
+            nonce: if spec.stale_nonce { nonce } else { nonce + 1 },
+            chain_id: DEFAULT_CHAIN_ID,
+            gasprice: Wei::from_wei(spec.gasprice),
+            max_fee: spec.max_fee.map(Wei::from_wei),
+            max_priority_fee: spec.max_priority_fee.map(Wei::from_wei),
+            gaslimit: spec.gaslimit,
+            recipient: spec.recipient.map(|index| self.addresses[index]),
+            value: Wei::from_wei(spec.value),
+            code: spec.code.clone(),
+            encoding: HashEncoding::Bincode,
+            ecdsa_fields: get_bs_ecsda_field(&self.secrets[spec.sender]),
+        };
+        if spec.forged {
+            txn.sign_transaction(&secret_key(ACCOUNTS));
+        } else {
+            txn.sign_transaction(&self.secrets[spec.sender]);
+        }
+        txn
+    }
+}
+
+fn accepted(outcome: &Result<(), FailureReason>) -> bool {
+    !outcome.is_err_and(|failure| failure.is_rejection())
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn wei_is_only_created_by_rewards(
+        balances in prop::collection::vec(0..2000u128, ACCOUNTS),
+        base_fee in 0..4u128,
+        actions in prop::collection::vec(action(), 1..16),
+    ) {
+        // burned base fees leave circulation but still count
+        let mut world = World::new(&balances, base_fee);
+        let mut expected_total = world.state().total_wei();
+        for action in &actions {
+            match action {
+                Action::Send(spec) => {
+                    let txn = world.build_txn(spec);
+                    world.evm_state.execute_transaction(&txn);
+                }
+                Action::RewardMiner => {
+                    let miner = world.addresses[0];
+                    world.evm_state.reward_miner(miner);
+                    expected_total += Wei::from_wei(MINING_REWARD);
+                }
+            }
+            prop_assert_eq!(
+                world.state().total_wei() + world.evm_state.get_burned(),
+                expected_total
+            );
+        }
+    }
+
+    #[test]
+    fn balances_never_go_negative(
+        balances in prop::collection::vec(0..2000u128, ACCOUNTS),
+        base_fee in 0..4u128,
+        specs in prop::collection::vec(txn_spec(), 1..16),
+    ) {
+        // Wei can't hold a negative amount, so an overdraft would show up
+        // as a wrapped balance larger than all the wei in existence
+        let mut world = World::new(&balances, base_fee);
+        let total = world.state().total_wei();
+        for spec in &specs {
+            let sender = world.addresses[spec.sender];
+            let before = world.state().get_value(&sender).unwrap();
+            let txn = world.build_txn(spec);
+            let receipt = world.evm_state.execute_transaction(&txn);
+            if receipt.outcome == Err(FailureReason::InsufficientBalance) && receipt.gas_used == 0 {
+                // the fee couldn't be covered, so nothing moved
+                prop_assert_eq!(world.state().get_value(&sender).unwrap(), before);
+            }
+            for addr in &world.addresses {
+                prop_assert!(world.state().get_value(addr).unwrap() <= total);
+            }
+        }
+    }
+
+    #[test]
+    fn nonces_increase_by_one_per_accepted_txn(
+        balances in prop::collection::vec(0..2000u128, ACCOUNTS),
+        base_fee in 0..4u128,
+        specs in prop::collection::vec(txn_spec(), 1..16),
+    ) {
+        let mut world = World::new(&balances, base_fee);
+        for spec in &specs {
+            let mut expected = world.nonces();
+            let txn = world.build_txn(spec);
+            let receipt = world.evm_state.execute_transaction(&txn);
+            if accepted(&receipt.outcome) {
+                expected[spec.sender] += 1;
+            } else {
+                let underpriced = txn.get_max_fee() < Wei::from_wei(base_fee);
+                prop_assert!(spec.stale_nonce || spec.forged || underpriced);
+            }
+            prop_assert_eq!(world.nonces(), expected);
+        }
+    }
+
+    #[test]
+    fn parallel_execution_matches_sequential(
+        balances in prop::collection::vec(0..2000u128, ACCOUNTS),
+        base_fee in 0..4u128,
+        specs in prop::collection::vec(txn_spec(), 1..24),
+        num_threads in 1..6usize,
+    ) {
+        let mut sequential = World::new(&balances, base_fee);
+        let txns = sequential.build_block(&specs);
+        let expected: Vec<_> = txns
+            .iter()
+            .map(|txn| sequential.evm_state.execute_transaction(txn))
+            .collect();
+
+        let mut parallel = World::new(&balances, base_fee);
+        let receipts = parallel.evm_state.execute_block_parallel(&txns, num_threads);
+        prop_assert_eq!(receipts, expected);
+        prop_assert_eq!(parallel.state(), sequential.state());
+        prop_assert_eq!(parallel.evm_state.get_burned(), sequential.evm_state.get_burned());
+    }
+}
+
+#[test]
+fn generated_code_decodes() {
+    // keeps OP_BYTES honest if opcodes are ever renumbered
+    for &byte in OP_BYTES {
+        if let Op::INVALID(_) = Op::from_byte(byte) {
+            panic!("0x{:02x} isn't an op", byte);
+        }
+    }
+}
@@ -0,0 +1,291 @@
+use std::collections::BTreeSet;
+
+use super::evmexec::{ExecutionContext, OperationResult};
+use super::gas::Gas;
+use super::ops::Op;
+use super::wei::Wei;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum DebugStatus {
+    Paused,
+    Breakpoint(u32),
+    Stopped,
+    Failed,
+}
+
+/// Single-steps an `ExecutionContext`. Rewinding replays the code from the
+/// start, since execution is deterministic.
+pub struct Debugger {
+    gaslimit: Gas,
+    code: Vec<Op>,
+    initial_value: Wei,
+    context: ExecutionContext,
+    steps: usize,
+    breakpoints: BTreeSet<u32>,
+    status: DebugStatus,
+}
+
+impl Debugger {
+    pub fn new(gaslimit: Gas, code: Vec<Op>, txn_value: Wei) -> Debugger {
+        Debugger {
+            context: ExecutionContext::new(gaslimit, code.clone(), txn_value),
+            gaslimit,
+            code,
+            initial_value: txn_value,
+            steps: 0,
+            breakpoints: BTreeSet::new(),
+            status: DebugStatus::Paused,
+        }
+    }
+
+    pub fn get_status(&self) -> DebugStatus {
+        self.status
+    }
+
+    pub fn get_steps(&self) -> usize {
+        self.steps
+    }
+
+    pub fn get_context(&self) -> &ExecutionContext {
+        &self.context
+    }
+
+    pub fn get_breakpoints(&self) -> &BTreeSet<u32> {
+        &self.breakpoints
+    }
+
+    pub fn add_breakpoint(&mut self, pc: u32) {
+        self.breakpoints.insert(pc);
+    }
+
+    pub fn remove_breakpoint(&mut self, pc: u32) -> bool {
+        self.breakpoints.remove(&pc)
+    }
+
+    // op at the current pc, if the code hasn't run off its end
+    pub fn current_op(&self) -> Option<Op> {
+        self.code.get(self.context.get_pc() as usize).cloned()
+    }
+
+    // execute a single op, unless the code has already terminated
+    pub fn step(&mut self) -> DebugStatus {
+        if self.is_terminated() {
+            return self.status;
+        }
+        self.steps += 1;
+        self.status = match self.context.step() {
+            Ok(OperationResult::Continue) => DebugStatus::Paused,
+            Ok(OperationResult::Stop) => DebugStatus::Stopped,
+            Err(_) => DebugStatus::Failed,
+        };
+        self.status
+    }
+
+    // run until a breakpoint is reached or the code terminates
+    pub fn continue_execution(&mut self) -> DebugStatus {
+        loop {
+            if let DebugStatus::Stopped | DebugStatus::Failed = self.step() {
+                return self.status;
+            }
+            let pc = self.context.get_pc();
+            if self.breakpoints.contains(&pc) {
+                self.status = DebugStatus::Breakpoint(pc);
+                return self.status;
+            }
+        }
+    }
+
+    // undo the last `count` steps by replaying the rest from the beginning
+    pub fn rewind(&mut self, count: usize) -> DebugStatus {
+        let target = self.steps.saturating_sub(count);
+        self.restart();
+        while self.steps < target {
+            self.step();
+        }
+        self.status
+    }
+
+    pub fn restart(&mut self) {
+        self.context = ExecutionContext::new(self.gaslimit, self.code.clone(), self.initial_value);
+        self.steps = 0;
+        self.status = DebugStatus::Paused;
+    }
+
+    /// Runs a single debugger command and returns the text to display.
+    pub fn run_command(&mut self, line: &str) -> String {
+        let mut words = line.split_whitespace();
+        let command = match words.next() {
+            Some(command) => command,
+            None => return String::new(),
+        };
+        let arg = words.next();
+        match command {
+            "s" | "step" => {
+                let count = match Self::parse_arg(arg, 1) {
+                    Ok(count) => count,
+                    Err(msg) => return msg,
+                };
+                for _ in 0..count {
+                    self.step();
+                }
+                self.describe()
+            }
+            "c" | "continue" => {
+                self.continue_execution();
+                self.describe()
+            }
+            "b" | "break" => match arg.map(str::parse::<u32>) {
+                Some(Ok(pc)) => {
+                    self.add_breakpoint(pc);
+                    format!("breakpoint set at pc {}", pc)
+                }
+                _ => "usage: break <pc>".to_string(),
+            },
+            "d" | "delete" => match arg.map(str::parse::<u32>) {
+                Some(Ok(pc)) => {
+                    if self.remove_breakpoint(pc) {
+                        format!("breakpoint at pc {} removed", pc)
+                    } else {
+                        format!("no breakpoint at pc {}", pc)
+                    }
+                }
+                _ => "usage: delete <pc>".to_string(),
+            },
+            "r" | "rewind" => {
+                let count = match Self::parse_arg(arg, 1) {
+                    Ok(count) => count,
+                    Err(msg) => return msg,
+                };
+                self.rewind(count);
+                self.describe()
+            }
+            "restart" => {
+                self.restart();
+                self.describe()
+            }
+            "stack" => format!("stack: {:?}", self.context.get_stack()),
+            "value" => format!("value: {}", self.context.get_value()),
+            "i" | "info" => format!(
+                "{}\nbreakpoints: {:?}",
+                self.describe(),
+                self.breakpoints.iter().collect::<Vec<_>>()
+            ),
+            "h" | "help" => HELP.to_string(),
+            _ => format!("unknown command '{}', try 'help'", command),
+        }
+    }
+
+    // one-line summary of where execution is paused
+    pub fn describe(&self) -> String {
+        let status = match self.status {
+            DebugStatus::Paused => "paused".to_string(),
+            DebugStatus::Breakpoint(pc) => format!("hit breakpoint at pc {}", pc),
+            DebugStatus::Stopped => "stopped".to_string(),
+            DebugStatus::Failed => "failed".to_string(),
+        };
+        let next = match self.current_op() {
+            Some(op) if !self.is_terminated() => format!(" next: {:?}", op),
+            _ => String::new(),
+        };
+        format!(
+            "[{}] step {} pc {} gas {} value {} stack {:?}{}",
+            status,
+            self.steps,
+            self.context.get_pc(),
+            self.context.get_gas_left(),
+            self.context.get_value().get_wei(),
+            self.context.get_stack(),
+            next
+        )
+    }
+
+    fn is_terminated(&self) -> bool {
+        matches!(self.status, DebugStatus::Stopped | DebugStatus::Failed)
+    }
+
+    fn parse_arg(arg: Option<&str>, default: usize) -> Result<usize, String> {
+        match arg {
+            None => Ok(default),
+            Some(arg) => arg
+                .parse()
+                .map_err(|_| format!("expected a count, got '{}'", arg)),
+        }
+    }
+}
+
+const HELP: &str = "commands:
+  s, step [n]      execute n ops (default 1)
+  c, continue      run until a breakpoint or termination
+  b, break <pc>    set a breakpoint
+  d, delete <pc>   remove a breakpoint
+  r, rewind [n]    undo n steps (default 1)
+  restart          rewind to the beginning
+  stack            print the stack
+  value            print the transaction value
+  i, info          print the machine state and breakpoints
+  q, quit          exit the debugger";
+
+#[cfg(test)]
+mod tests {
+    use super::{super::ops::Op::*, super::wei::Wei, DebugStatus, Debugger};
+
+    fn sample_debugger() -> Debugger {
+        let ops = vec![PUSH1(2), PUSH1(3), ADD, ADDVAL, STOP];
+        Debugger::new(100, ops, Wei::from_wei(10))
+    }
+
+    #[test]
+    fn step_and_inspect() {
+        let mut debugger = sample_debugger();
+        assert_eq!(debugger.step(), DebugStatus::Paused);
+        assert_eq!(debugger.step(), DebugStatus::Paused);
+        assert_eq!(debugger.get_context().get_stack(), &[2, 3]);
+        assert_eq!(debugger.step(), DebugStatus::Paused);
+        assert_eq!(debugger.get_context().get_stack(), &[5]);
+        assert_eq!(debugger.step(), DebugStatus::Paused);
+        assert_eq!(debugger.get_context().get_value(), Wei::from_wei(15));
+        assert_eq!(debugger.step(), DebugStatus::Stopped);
+        // further steps are no-ops
+        assert_eq!(debugger.step(), DebugStatus::Stopped);
+        assert_eq!(debugger.get_steps(), 5);
+    }
+
+    #[test]
+    fn breakpoints_and_rewind() {
+        let mut debugger = sample_debugger();
+        debugger.add_breakpoint(3);
+        assert_eq!(debugger.continue_execution(), DebugStatus::Breakpoint(3));
+        assert_eq!(debugger.get_context().get_stack(), &[5]);
+
+        debugger.rewind(2);
+        assert_eq!(debugger.get_context().get_pc(), 1);
+        assert_eq!(debugger.get_context().get_stack(), &[2]);
+
+        assert!(debugger.remove_breakpoint(3));
+        assert_eq!(debugger.continue_execution(), DebugStatus::Stopped);
+        assert_eq!(debugger.get_context().get_value(), Wei::from_wei(15));
+    }
+
+    #[test]
+    fn commands() {
+        let mut debugger = sample_debugger();
+        assert_eq!(debugger.run_command("break 2"), "breakpoint set at pc 2");
+        assert!(debugger
+            .run_command("c")
+            .starts_with("[hit breakpoint at pc 2]"));
+        assert_eq!(debugger.run_command("stack"), "stack: [2, 3]");
+        debugger.run_command("step 2");
+        assert_eq!(debugger.run_command("value"), "value: 15 wei");
+        debugger.run_command("rewind 4");
+        assert_eq!(debugger.get_steps(), 0);
+        assert_eq!(debugger.run_command("delete 7"), "no breakpoint at pc 7");
+        assert!(debugger.run_command("bogus").starts_with("unknown command"));
+    }
+
+    #[test]
+    fn failure_is_reported() {
+        let mut debugger = Debugger::new(100, vec![PUSH1(1), ADD], Wei::from_wei(0));
+        assert_eq!(debugger.continue_execution(), DebugStatus::Failed);
+        assert_eq!(debugger.get_steps(), 2);
+    }
+}
@@ -0,0 +1,154 @@
+use std::collections::BTreeSet;
+
+use super::ops::Op;
+
+/// A decoded op along with where it came from. `index` is the op's position
+/// in `Op::from_bytes` output, which is what JUMP targets refer to; a
+/// truncated PUSH1 is dropped from that output, so it has none.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Instruction {
+    pub offset: usize,
+    pub index: Option<usize>,
+    pub op: Op,
+    // a PUSH1 at the very end of the code with no operand byte
+    pub truncated: bool,
+}
+
+pub fn decode(bytes: &[u8]) -> Vec<Instruction> {
+    let mut instructions = Vec::new();
+    let mut offset = 0;
+    while offset < bytes.len() {
+        let mut instruction = Instruction {
+            offset,
+            index: Some(instructions.len()),
+            op: Op::from_byte(bytes[offset]),
+            truncated: false,
+        };
+        offset += 1;
+        if let Op::PUSH1(_) = instruction.op {
+            match bytes.get(offset) {
+                Some(val) => {
+                    instruction.op = Op::PUSH1(*val);
+                    offset += 1;
+                }
+                None => {
+                    instruction.index = None;
+                    instruction.truncated = true;
+                }
+            }
+        }
+        instructions.push(instruction);
+    }
+    instructions
+}
+
+// op index pushed right before a JUMP or JUMPI
+fn constant_jump_target(instructions: &[Instruction], i: usize) -> Option<usize> {
+    match (
+        instructions[i].op,
+        instructions.get(i + 1).map(|next| next.op),
+    ) {
+        (Op::PUSH1(target), Some(Op::JUMP)) | (Op::PUSH1(target), Some(Op::JUMPI)) => {
+            Some(usize::from(target))
+        }
+        _ => None,
+    }
+}
+
+pub fn format_op(op: Op) -> String {
+    match op {
+        Op::PUSH1(val) => format!("PUSH1 0x{:02x}", val),
+        Op::INVALID(byte) => format!("INVALID(0x{:02x})", byte),
+        op => op.mnemonic().to_string(),
+    }
+}
+
+/// Renders bytecode as `offset: MNEMONIC immediate` lines. Constant jumps
+/// are annotated with their destination, and destinations are marked.
+pub fn disassemble(bytes: &[u8]) -> String {
+    let instructions = decode(bytes);
+    let targets: BTreeSet<usize> = (0..instructions.len())
+        .filter_map(|i| constant_jump_target(&instructions, i))
+        .collect();
+
+    let mut output = String::new();
+    for (i, instruction) in instructions.iter().enumerate() {
+        let mut comments = vec![];
+        if let Some(index) = instruction.index.filter(|index| targets.contains(index)) {
+            comments.push(format!("jumpdest (op {})", index));
+        }
+        let text = if instruction.truncated {
+            comments.push("missing PUSH1 operand".to_string());
+            instruction.op.mnemonic().to_string()
+        } else {
+            format_op(instruction.op)
+        };
+        if let Some(target) = constant_jump_target(&instructions, i) {
+            let dest = instructions
+                .get(target)
+                .filter(|dest| dest.index == Some(target));
+            comments.push(match dest {
+                Some(dest) => format!("jump to op {} at {:04x}", target, dest.offset),
+                None => format!("jump to op {} (out of range)", target),
+            });
+        }
+
+        output.push_str(&format!("{:04x}: {}", instruction.offset, text));
+        if !comments.is_empty() {
+            output.push_str(&format!("  ; {}", comments.join(", ")));
+        }
+        output.push('\n');
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{super::ops::Op, decode, disassemble};
+
+    #[test]
+    fn test_offsets_and_indices() {
+        let instructions = decode(&[0x60, 2, 0xb1, 0x00]);
+        assert_eq!(instructions.len(), 3);
+        assert_eq!(instructions[1].offset, 2);
+        assert_eq!(instructions[1].index, Some(1));
+        assert_eq!(instructions[1].op, Op::ADDVAL);
+    }
+
+    #[test]
+    fn test_disassemble() {
+        // [PUSH1(100), PUSH1(4), JUMPI, INVALID(0x05), STOP, PUSH1]
+        let code = vec![0x60, 100, 0x60, 4, 0x57, 0x05, 0x00, 0x60];
+        let expected = "\
+0000: PUSH1 0x64
+0002: PUSH1 0x04  ; jump to op 4 at 0006
+0004: JUMPI
+0005: INVALID(0x05)
+0006: STOP  ; jumpdest (op 4)
+0007: PUSH1  ; missing PUSH1 operand
+";
+        assert_eq!(disassemble(&code), expected);
+    }
+
+    #[test]
+    fn test_out_of_range_jump() {
+        let code = vec![0x60, 9, 0x56];
+        assert_eq!(
+            disassemble(&code),
+            "0000: PUSH1 0x09  ; jump to op 9 (out of range)\n0002: JUMP\n"
+        );
+    }
+
+    #[test]
+    fn test_truncated_push_is_not_an_op() {
+        // Op::from_bytes gives [PUSH1(2), JUMP], so op 2 doesn't exist
+        let code = vec![0x60, 2, 0x56, 0x60];
+        assert_eq!(Op::from_bytes(&code).len(), 2);
+        assert_eq!(decode(&code)[2].index, None);
+        assert_eq!(
+            disassemble(&code),
+            "0000: PUSH1 0x02  ; jump to op 2 (out of range)\n0002: JUMP\n\
+             0003: PUSH1  ; missing PUSH1 operand\n"
+        );
+    }
+}
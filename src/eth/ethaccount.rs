@@ -1,11 +1,13 @@
 use super::aliases::ETHAddress;
 use super::wei::Wei;
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub struct ETHAccount {
     pub address: ETHAddress,
     pub balance: Wei,
     pub nonce: u32,
+    // runtime code, only ever set for accounts created by a transaction
+    pub code: Vec<u8>,
 }
 
 impl ETHAccount {
@@ -14,6 +16,7 @@ impl ETHAccount {
             address: addr,
             balance: Wei::from_wei(0),
             nonce: 0,
+            code: vec![],
         }
     }
 }
@@ -1,27 +1,62 @@
 use bincode::serialize;
 use rand::Rng;
-use sha3::{Digest, Sha3_256};
+use sha3::{Digest, Keccak256, Sha3_256};
 
 use super::aliases::{BlockHash, ETHAddress, ProofOfWork};
+use super::bloom::Bloom;
+use super::ethreceipt::ETHReceipt;
 use super::ethtxn::ETHTxn;
+use super::gas::Gas;
+use super::rlp::{HashEncoding, Rlp};
+use super::wei::Wei;
+
+pub const GENESIS_GAS_LIMIT: Gas = 10_000_000;
+pub const MIN_GAS_LIMIT: Gas = 5000;
+// a block's gas limit may differ from its parent's by at most 1/1024
+pub const GAS_LIMIT_BOUND_DIVISOR: Gas = 1024;
 
 #[derive(Debug, Serialize, Clone)]
 pub struct ETHBlock {
     prev_hash: BlockHash,
     miner_id: ETHAddress,
     transactions: Vec<ETHTxn>,
+    logs_bloom: Bloom,
+    // zero before a fee market is in place
+    base_fee: Wei,
+    gas_limit: Gas,
+    gas_used: Gas,
     difficulty: u32,
     nonce: ProofOfWork,
+
+    // receipts are committed to through the logs bloom
+    #[serde(skip_serializing)]
+    receipts: Vec<ETHReceipt>,
+    // what the block is hashed as
+    #[serde(skip_serializing)]
+    encoding: HashEncoding,
 }
 
 impl ETHBlock {
     pub fn genesis() -> ETHBlock {
+        ETHBlock::genesis_with_gas_limit(GENESIS_GAS_LIMIT)
+    }
+
+    // a limit below the minimum is raised to it, since no child could make
+    // up the difference in one step
+    pub fn genesis_with_gas_limit(gas_limit: Gas) -> ETHBlock {
+        let gas_limit = gas_limit.max(MIN_GAS_LIMIT);
         ETHBlock {
             prev_hash: [0; 32],
             miner_id: [0; 20],
             transactions: vec![],
+            logs_bloom: Bloom::new(),
+            base_fee: Wei::from_wei(0),
+            gas_limit,
+            gas_used: 0,
             difficulty: 0,
             nonce: 0,
+            receipts: vec![],
+            encoding: HashEncoding::Bincode,
         }
     }
 
@@ -29,14 +64,30 @@ impl ETHBlock {
         prev_block: &ETHBlock,
         miner_id: ETHAddress,
         difficulty: u32,
+        base_fee: Wei,
+        gas_limit: Gas,
         transactions: Vec<ETHTxn>,
+        receipts: Vec<ETHReceipt>,
     ) -> ETHBlock {
+        let mut logs_bloom = Bloom::new();
+        let mut gas_used = 0;
+        for receipt in &receipts {
+            logs_bloom.accrue_bloom(&receipt.logs_bloom);
+            gas_used += receipt.gas_used;
+        }
         ETHBlock {
             prev_hash: prev_block.hash(),
             miner_id,
             transactions,
+            logs_bloom,
+            base_fee,
+            gas_limit,
+            gas_used,
             difficulty,
             nonce: 0,
+            receipts,
+            // the parent's, unless it's changed with `set_encoding`
+            encoding: prev_block.encoding,
         }
     }
 
@@ -44,16 +95,52 @@ impl ETHBlock {
         serialize(self).unwrap()
     }
 
+    /// The header as Ethereum would encode it. The transactions are
+    /// committed to by the Keccak hash of the list of their RLP encodings.
+    pub fn rlp_header(&self) -> Vec<u8> {
+        let transactions = Rlp::List(
+            self.transactions
+                .iter()
+                .map(|txn| Rlp::bytes(&txn.rlp_encoding()))
+                .collect(),
+        );
+        Rlp::List(vec![
+            Rlp::bytes(&self.prev_hash),
+            Rlp::bytes(&self.miner_id),
+            Rlp::bytes(&Keccak256::digest(&transactions.encode())),
+            Rlp::bytes(self.logs_bloom.as_bytes()),
+            Rlp::uint(self.base_fee.get_wei()),
+            Rlp::uint(self.gas_limit),
+            Rlp::uint(self.gas_used),
+            Rlp::uint(u128::from(self.difficulty)),
+            Rlp::uint(u128::from(self.nonce)),
+        ])
+        .encode()
+    }
+
     pub fn hash(&self) -> BlockHash {
-        let mut hasher = Sha3_256::default();
-        hasher.input(self.binary_serialization());
         let mut result: BlockHash = [0; 32];
-        let hasher_result = hasher.result();
-        let slice = hasher_result.as_slice();
-        result.copy_from_slice(slice);
+        match self.encoding {
+            HashEncoding::Bincode => {
+                let mut hasher = Sha3_256::default();
+                hasher.input(self.binary_serialization());
+                result.copy_from_slice(hasher.result().as_slice());
+            }
+            HashEncoding::Rlp => {
+                result.copy_from_slice(&Keccak256::digest(&self.rlp_header()));
+            }
+        }
         result
     }
 
+    pub fn set_encoding(&mut self, encoding: HashEncoding) {
+        self.encoding = encoding;
+    }
+
+    pub fn get_encoding(&self) -> HashEncoding {
+        self.encoding
+    }
+
     pub fn randomize_nonce(&mut self, rng: &mut rand::ThreadRng) {
         self.nonce = rng.next_u32();
     }
@@ -85,11 +172,54 @@ impl ETHBlock {
     pub fn get_miner_address(&self) -> ETHAddress {
         self.miner_id
     }
+
+    pub fn get_logs_bloom(&self) -> &Bloom {
+        &self.logs_bloom
+    }
+
+    pub fn get_base_fee(&self) -> Wei {
+        self.base_fee
+    }
+
+    pub fn get_gas_limit(&self) -> Gas {
+        self.gas_limit
+    }
+
+    // the gas limit a child block may have closest to `target`
+    pub fn next_gas_limit(&self, target: Gas) -> Gas {
+        let max_delta = self.gas_limit / GAS_LIMIT_BOUND_DIVISOR;
+        let limit = if target > self.gas_limit {
+            self.gas_limit + (target - self.gas_limit).min(max_delta)
+        } else {
+            self.gas_limit - (self.gas_limit - target).min(max_delta)
+        };
+        limit.max(MIN_GAS_LIMIT)
+    }
+
+    // whether `child` keeps to the gas limit rules relative to this block
+    pub fn valid_child_gas(&self, child: &ETHBlock) -> bool {
+        let delta = child.gas_limit.abs_diff(self.gas_limit);
+        delta <= self.gas_limit / GAS_LIMIT_BOUND_DIVISOR
+            && child.gas_limit >= MIN_GAS_LIMIT
+            && child.gas_used <= child.gas_limit
+    }
+
+    pub fn get_gas_used(&self) -> Gas {
+        self.gas_used
+    }
+
+    pub fn get_transactions(&self) -> &[ETHTxn] {
+        &self.transactions
+    }
+
+    pub fn get_receipts(&self) -> &[ETHReceipt] {
+        &self.receipts
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::ETHBlock;
+    use super::{super::ethreceipt::ETHReceipt, ETHBlock, MIN_GAS_LIMIT};
 
     #[test]
     fn test_hashing() {
@@ -108,4 +238,36 @@ mod tests {
         assert!(ETHBlock::valid_hash(hash3, 16 + 6));
         assert!(!ETHBlock::valid_hash(hash3, 16 + 7));
     }
+
+    #[test]
+    fn gas_limit_moves_toward_target() {
+        // may move by 1024 either way
+        let parent = ETHBlock::genesis_with_gas_limit(1 << 20);
+        assert_eq!(parent.next_gas_limit(1 << 20), 1 << 20);
+        assert_eq!(parent.next_gas_limit(1 << 21), (1 << 20) + 1024);
+        assert_eq!(parent.next_gas_limit((1 << 20) + 7), (1 << 20) + 7);
+        assert_eq!(parent.next_gas_limit(0), (1 << 20) - 1024);
+        let small = ETHBlock::genesis_with_gas_limit(MIN_GAS_LIMIT);
+        assert_eq!(small.next_gas_limit(0), MIN_GAS_LIMIT);
+        let tiny = ETHBlock::genesis_with_gas_limit(100);
+        assert_eq!(tiny.get_gas_limit(), MIN_GAS_LIMIT);
+
+        let child = |gas_limit, gas_used| {
+            let receipt = ETHReceipt::new(Ok(()), gas_used, vec![]);
+            ETHBlock::new(
+                &parent,
+                [0; 20],
+                0,
+                Default::default(),
+                gas_limit,
+                vec![],
+                vec![receipt],
+            )
+        };
+        assert!(parent.valid_child_gas(&child((1 << 20) + 1024, (1 << 20) + 1024)));
+        assert!(parent.valid_child_gas(&child((1 << 20) - 1024, 0)));
+        assert!(!parent.valid_child_gas(&child((1 << 20) + 1025, 0)));
+        assert!(!parent.valid_child_gas(&child((1 << 20) - 1025, 0)));
+        assert!(!parent.valid_child_gas(&child(1 << 20, (1 << 20) + 1)));
+    }
 }
@@ -1,54 +1,219 @@
-use super::aliases::ETHAddress;
-use super::ethblock::ETHBlock;
+use std::collections::HashSet;
+
+use super::aliases::{ETHAddress, TxnHash};
+use super::analyzer;
+use super::ethblock::{ETHBlock, GENESIS_GAS_LIMIT};
+use super::ethreceipt::ETHLog;
 use super::ethstate::ETHState;
 use super::ethtxn::ETHTxn;
-use super::evmstate::{EVMState, FailureReason};
+use super::evmstate::EVMState;
+use super::feemarket::FeeMarket;
+use super::gas::Gas;
+use super::gasschedule::ForkSchedule;
+use super::ops::Op;
+use super::rlp::HashEncoding;
+use super::txpool::TxPool;
+use super::wei::Wei;
 
 pub struct ETHBlockchain {
-    block_txn_limit: usize,
+    // an optional cap on transactions per block, on top of the gas limit
+    block_txn_limit: Option<usize>,
+    // the gas limit miners vote for, if not keeping the parent's
+    gas_limit_target: Option<Gas>,
     miner: ETHAddress,
     difficulty: u32,
-    outstanding_txns: Vec<ETHTxn>,
+    pool: TxPool,
+    validate_code: bool,
+    gas_schedule: ForkSchedule,
+    fee_market: Option<FeeMarket>,
     evmstate: EVMState,
     blocks: Vec<ETHBlock>,
+    // hashes of every transaction in `blocks`
+    included: HashSet<TxnHash>,
 }
 
 impl ETHBlockchain {
     pub fn new(block_txn_limit: usize, difficulty: u32, miner: ETHAddress) -> ETHBlockchain {
+        let mut block_chain = ETHBlockchain::with_gas_limit(GENESIS_GAS_LIMIT, difficulty, miner);
+        block_chain.block_txn_limit = Some(block_txn_limit);
+        block_chain
+    }
+
+    // blocks are only limited by gas, starting from `gas_limit` or
+    // MIN_GAS_LIMIT, whichever is higher
+    pub fn with_gas_limit(gas_limit: Gas, difficulty: u32, miner: ETHAddress) -> ETHBlockchain {
         ETHBlockchain {
-            block_txn_limit,
+            block_txn_limit: None,
+            gas_limit_target: None,
             miner,
             difficulty,
-            outstanding_txns: vec![],
+            pool: TxPool::default(),
+            validate_code: false,
+            gas_schedule: ForkSchedule::default(),
+            fee_market: None,
             evmstate: EVMState::new(ETHState::default(), miner),
-            blocks: vec![ETHBlock::genesis()],
+            blocks: vec![ETHBlock::genesis_with_gas_limit(gas_limit)],
+            included: HashSet::new(),
         }
     }
 
-    // returns a block with an uncomputed nonce
+    // when enabled, transactions whose code can never succeed are rejected
+    // before they're executed
+    pub fn set_code_validation(&mut self, enabled: bool) {
+        self.validate_code = enabled;
+    }
+
+    // transactions must be signed for this chain to be accepted
+    pub fn set_chain_id(&mut self, chain_id: u32) {
+        self.evmstate.set_chain_id(chain_id);
+    }
+
+    pub fn get_chain_id(&self) -> u32 {
+        self.evmstate.get_chain_id()
+    }
+
+    // what transactions are signed over and blocks are hashed as from now on
+    pub fn set_encoding(&mut self, encoding: HashEncoding) {
+        self.evmstate.set_encoding(encoding);
+    }
+
+    pub fn get_encoding(&self) -> HashEncoding {
+        self.evmstate.get_encoding()
+    }
+
+    pub fn set_gas_schedule(&mut self, schedule: ForkSchedule) {
+        self.gas_schedule = schedule;
+    }
+
+    pub fn set_block_txn_limit(&mut self, limit: Option<usize>) {
+        self.block_txn_limit = limit;
+    }
+
+    // blocks built from now on move their gas limit toward `target`
+    pub fn set_gas_limit_target(&mut self, target: Gas) {
+        self.gas_limit_target = Some(target);
+    }
+
+    // the gas limit of the next block built on the current chain
+    pub fn next_gas_limit(&self) -> Gas {
+        // safe to unwrap due to genesis block
+        let parent = self.blocks.last().unwrap();
+        parent.next_gas_limit(
+            self.gas_limit_target
+                .unwrap_or_else(|| parent.get_gas_limit()),
+        )
+    }
+
+    // blocks built from now on burn a base fee, and miners only get tips
+    pub fn set_fee_market(&mut self, market: FeeMarket) {
+        self.fee_market = Some(market);
+    }
+
+    // the base fee a block built on the current chain must have
+    pub fn next_base_fee(&self) -> Wei {
+        // safe to unwrap due to genesis block
+        let parent = self.blocks.last().unwrap();
+        match self.fee_market {
+            Some(market) => market.next_base_fee(parent.get_base_fee(), parent.get_gas_used()),
+            None => Wei::from_wei(0),
+        }
+    }
+
+    pub fn get_burned(&self) -> Wei {
+        self.evmstate.get_burned()
+    }
+
+    // transactions beyond new limits already in the pool stay there
+    pub fn set_pool_limits(&mut self, max_size: usize, max_per_sender: usize) {
+        self.pool.set_limits(max_size, max_per_sender);
+    }
+
+    // how much more, in percent, a transaction replacing a pooled one with
+    // the same sender and nonce has to pay
+    pub fn set_price_bump(&mut self, percent: u32) {
+        self.pool.set_price_bump(percent);
+    }
+
+    pub fn get_pool(&self) -> &TxPool {
+        &self.pool
+    }
+
+    // executes the best pending transactions and returns a block with an
+    // uncomputed nonce
     pub fn flush_txns(&mut self) -> ETHBlock {
-        let txns = std::mem::replace(&mut self.outstanding_txns, vec![]);
-        ETHBlock::new(
+        // transactions are charged by the schedule of the block they land in
+        self.evmstate
+            .set_gas_schedule(self.gas_schedule.at_block(self.blocks.len()));
+        let base_fee = self.next_base_fee();
+        self.evmstate.set_base_fee(base_fee);
+        let gas_limit = self.next_gas_limit();
+        let mut gas_left = gas_limit;
+        let mut receipts = vec![];
+        let evmstate = &mut self.evmstate;
+        let max_txns = self.block_txn_limit.unwrap_or(usize::MAX);
+        // a transaction that might not fit waits for the next block
+        let taken = self.pool.take_best_by(max_txns, base_fee, |_, txn| {
+            if txn.gaslimit > gas_left {
+                return false;
+            }
+            let receipt = evmstate.execute_transaction(txn);
+            gas_left -= receipt.gas_used;
+            receipts.push(receipt);
+            true
+        });
+        let mut txns = vec![];
+        let mut kept_receipts = vec![];
+        for ((_, txn), receipt) in taken.into_iter().zip(receipts) {
+            // checked when the transaction was pooled
+            if receipt.outcome.is_err_and(|failure| failure.is_rejection()) {
+                continue;
+            }
+            txns.push(txn);
+            kept_receipts.push(receipt);
+        }
+        let mut block = ETHBlock::new(
             // safe to unwrap due to genesis block
             self.blocks.last().unwrap(),
             self.miner,
             self.difficulty,
+            base_fee,
+            gas_limit,
             txns,
-        )
+            kept_receipts,
+        );
+        block.set_encoding(self.get_encoding());
+        block
     }
 
-    // if the transactions hit the limit, create a new unvalidated block
+    // pools the transaction, and once enough are pending, creates a new
+    // unvalidated block from them
     pub fn process_transaction(&mut self, txn: ETHTxn) -> Option<ETHBlock> {
-        if let Err(txn_failure) = self.evmstate.run_transaction(&txn) {
-            match txn_failure {
-                FailureReason::InvalidSignature | FailureReason::InvalidNonce => {
-                    return None;
-                }
-                _ => {}
+        if self.validate_code {
+            let schedule = self.gas_schedule.at_block(self.blocks.len());
+            if !analyzer::analyze(&Op::from_bytes(&txn.code), &schedule).can_succeed {
+                return None;
             }
         }
-        self.outstanding_txns.push(txn);
-        if self.outstanding_txns.len() >= self.block_txn_limit {
+        // anything that can't be executed never enters the pool, including
+        // a fee that doesn't fit in Wei
+        if txn.chain_id != self.get_chain_id()
+            || txn.encoding != self.get_encoding()
+            || txn.gaslimit > self.next_gas_limit()
+            || (txn.get_max_fee() * txn.gaslimit).is_none()
+        {
+            return None;
+        }
+        if self.included.contains(&txn.hash()) {
+            return None;
+        }
+        let sender = txn.get_sender_addr().ok()?;
+        let state_nonce = self.evmstate.get_world_state().get_nonce(&sender)?;
+        let base_fee = self.next_base_fee();
+        self.pool.add(txn, sender, state_nonce, base_fee).ok()?;
+        let txn_cap_reached = self
+            .block_txn_limit
+            .is_some_and(|limit| self.pool.pending_len() >= limit);
+        if txn_cap_reached || self.pool.pending_gas(self.next_base_fee()) >= self.next_gas_limit() {
             Some(self.flush_txns())
         } else {
             None
@@ -56,18 +221,98 @@ impl ETHBlockchain {
     }
 
     pub fn add_block(&mut self, block: ETHBlock) -> bool {
-        if !block.is_valid() {
+        // safe to unwrap due to genesis block
+        let parent = self.blocks.last().unwrap();
+        if !block.is_valid()
+            || block.get_base_fee() != self.next_base_fee()
+            || !parent.valid_child_gas(&block)
+            || block.get_encoding() != self.get_encoding()
+        {
             return false;
         }
+        // no transaction may appear twice, in this block or across blocks
+        let mut hashes = HashSet::new();
+        for txn in block.get_transactions() {
+            let hash = txn.hash();
+            if self.included.contains(&hash) || !hashes.insert(hash) {
+                return false;
+            }
+        }
+        self.included.extend(hashes);
         self.evmstate.reward_miner(block.get_miner_address());
         self.blocks.push(block);
         true
     }
+
+    // returns matching logs along with the number of the block containing them
+    pub fn filter_logs(
+        &self,
+        address: Option<&ETHAddress>,
+        topic: Option<u8>,
+    ) -> Vec<(usize, &ETHLog)> {
+        let mut matches = vec![];
+        for (number, block) in self.blocks.iter().enumerate() {
+            // the bloom can rule out a block without scanning its receipts
+            let bloom = block.get_logs_bloom();
+            if address.is_some_and(|addr| !bloom.contains_address(addr))
+                || topic.is_some_and(|t| !bloom.contains_topic(t))
+            {
+                continue;
+            }
+            for receipt in block.get_receipts() {
+                for log in &receipt.logs {
+                    if log.matches(address, topic) {
+                        matches.push((number, log));
+                    }
+                }
+            }
+        }
+        matches
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{super::ETHTxn, ETHBlockchain};
+    use sha3::{Digest, Keccak256};
+
+    use super::{
+        super::ETHAddress, super::ETHBlock, super::ETHTxn, super::FeeMarket, super::ForkSchedule,
+        super::HashEncoding, super::Wei, super::DEFAULT_CHAIN_ID, super::MIN_GAS_LIMIT,
+        ETHBlockchain,
+    };
+
+    fn keypair(seed: u8) -> (secp256k1::SecretKey, ETHAddress) {
+        let secretkey = secp256k1::SecretKey::parse(&[seed; 32]).unwrap();
+        let pubkey = secp256k1::PublicKey::from_secret_key(&secretkey);
+        (
+            secretkey,
+            ETHTxn::get_address_from_public_key(&pubkey).unwrap(),
+        )
+    }
+
+    fn signed_txn(
+        secretkey: &secp256k1::SecretKey,
+        nonce: u32,
+        gasprice: u128,
+        recipient: ETHAddress,
+        value: u128,
+    ) -> ETHTxn {
+        let mut txn = ETHTxn {
+            nonce,
+            chain_id: DEFAULT_CHAIN_ID,
+            gasprice: Wei::from_wei(gasprice),
+            max_fee: None,
+            max_priority_fee: None,
+            gaslimit: 10,
+            recipient: Some(recipient),
+            value: Wei::from_wei(value),
+            code: vec![],
+            encoding: HashEncoding::Bincode,
+            ecdsa_fields: super::super::ethtxn::utils::get_bs_ecsda_field(secretkey),
+        };
+        txn.sign_transaction(secretkey);
+        txn
+    }
 
     #[test]
     #[ignore]
@@ -87,4 +332,404 @@ mod tests {
         }
         println!("Ran for {:?} iterations.", iteration);
     }
+
+    #[test]
+    fn filter_logs_by_address_and_topic() {
+        let (secretkey, address) = keypair(1);
+
+        // difficulty 0 accepts any nonce, and the block reward funds the sender
+        let mut block_chain = ETHBlockchain::new(1, 0, address);
+        let block = block_chain.flush_txns();
+        assert!(block_chain.add_block(block));
+
+        let mut txn = signed_txn(&secretkey, 1, 1, [7; 20], 0);
+        txn.gaslimit = 800;
+        // [PUSH1(3), PUSH1(1), LOG1, STOP]
+        txn.code = vec![0x60, 3, 0x60, 1, 0xa1, 0x00];
+        txn.sign_transaction(&secretkey);
+        let block = block_chain.process_transaction(txn).unwrap();
+        assert!(block.get_logs_bloom().contains_topic(3));
+        assert!(block_chain.add_block(block));
+
+        let logs = block_chain.filter_logs(Some(&address), Some(3));
+        assert_eq!(logs.len(), 1);
+        assert_eq!(logs[0].0, 2);
+        assert_eq!(logs[0].1.data, 1);
+        assert_eq!(block_chain.filter_logs(None, Some(3)).len(), 1);
+        assert!(block_chain.filter_logs(Some(&[7; 20]), None).is_empty());
+        assert!(block_chain.filter_logs(None, Some(4)).is_empty());
+    }
+
+    #[test]
+    fn code_validation_rejects_doomed_code() {
+        let (secretkey, address) = keypair(1);
+
+        let mut block_chain = ETHBlockchain::new(1, 0, address);
+        block_chain.set_code_validation(true);
+        let block = block_chain.flush_txns();
+        assert!(block_chain.add_block(block));
+
+        let mut txn = signed_txn(&secretkey, 1, 1, [7; 20], 0);
+        txn.gaslimit = 100;
+        // [ADD, STOP] always underflows
+        txn.code = vec![0x01, 0x00];
+        txn.sign_transaction(&secretkey);
+        assert!(block_chain.process_transaction(txn.clone()).is_none());
+        assert!(block_chain.flush_txns().get_receipts().is_empty());
+
+        // [PUSH1(1), ADDVAL, STOP]
+        txn.code = vec![0x60, 1, 0xb1, 0x00];
+        txn.sign_transaction(&secretkey);
+        assert!(block_chain.process_transaction(txn).is_some());
+    }
+
+    #[test]
+    fn gas_schedule_forks_by_block() {
+        let (secretkey, address) = keypair(1);
+
+        let mut block_chain = ETHBlockchain::new(1, 0, address);
+        block_chain.set_gas_schedule(ForkSchedule::parse("from block 3\nADDVAL 50").unwrap());
+        let block = block_chain.flush_txns();
+        assert!(block_chain.add_block(block));
+
+        let mut txn = signed_txn(&secretkey, 1, 1, [7; 20], 0);
+        txn.gaslimit = 100;
+        // [PUSH1(1), ADDVAL, STOP]
+        txn.code = vec![0x60, 1, 0xb1, 0x00];
+        txn.sign_transaction(&secretkey);
+        let block = block_chain.process_transaction(txn.clone()).unwrap();
+        assert_eq!(block.get_receipts()[0].gas_used, 3 + 2);
+        assert!(block_chain.add_block(block));
+
+        txn.nonce += 1;
+        txn.sign_transaction(&secretkey);
+        let block = block_chain.process_transaction(txn).unwrap();
+        assert_eq!(block.get_receipts()[0].gas_used, 3 + 50);
+    }
+
+    #[test]
+    fn chain_id_is_configurable() {
+        let (alice_key, alice) = keypair(1);
+        let (_, bob) = keypair(2);
+        let mut block_chain = ETHBlockchain::new(1, 0, alice);
+        assert_eq!(block_chain.get_chain_id(), DEFAULT_CHAIN_ID);
+        block_chain.set_chain_id(7);
+        let block = block_chain.flush_txns();
+        assert!(block_chain.add_block(block));
+
+        // signed for the default chain, so this one won't take it
+        let mut txn = signed_txn(&alice_key, 1, 1, bob, 0);
+        assert!(block_chain.process_transaction(txn.clone()).is_none());
+        assert!(block_chain.get_pool().is_empty());
+
+        txn.chain_id = 7;
+        txn.sign_transaction(&alice_key);
+        let block = block_chain.process_transaction(txn).unwrap();
+        assert!(block.get_receipts()[0].outcome.is_ok());
+    }
+
+    #[test]
+    fn rlp_chain_hashes_rlp_headers() {
+        let (alice_key, alice) = keypair(1);
+        let (_, bob) = keypair(2);
+        let mut block_chain = ETHBlockchain::new(1, 0, alice);
+        block_chain.set_encoding(HashEncoding::Rlp);
+        let block = block_chain.flush_txns();
+        assert!(block_chain.add_block(block));
+
+        // signed over bincode, so not for this chain
+        let mut txn = signed_txn(&alice_key, 1, 1, bob, 0);
+        assert!(block_chain.process_transaction(txn.clone()).is_none());
+
+        txn.encoding = HashEncoding::Rlp;
+        txn.sign_transaction(&alice_key);
+        let block = block_chain.process_transaction(txn).unwrap();
+        assert!(block.get_receipts()[0].outcome.is_ok());
+        let hash = block.hash();
+        assert_eq!(&hash[..], &Keccak256::digest(&block.rlp_header())[..]);
+        assert!(block_chain.add_block(block));
+        let child = block_chain.flush_txns();
+        assert_eq!(child.get_encoding(), HashEncoding::Rlp);
+        assert!(block_chain.add_block(child));
+
+        // blocks hashed any other way aren't accepted
+        let mut block = block_chain.flush_txns();
+        block.set_encoding(HashEncoding::Bincode);
+        assert!(!block_chain.add_block(block));
+    }
+
+    #[test]
+    fn blocks_cannot_repeat_transactions() {
+        let (alice_key, alice) = keypair(1);
+        let (_, bob) = keypair(2);
+        let mut block_chain = ETHBlockchain::new(1, 0, alice);
+        let block = block_chain.flush_txns();
+        assert!(block_chain.add_block(block));
+
+        let txn = signed_txn(&alice_key, 1, 1, bob, 0);
+        let block = block_chain.process_transaction(txn.clone()).unwrap();
+        assert!(block_chain.add_block(block));
+        assert!(block_chain.process_transaction(txn.clone()).is_none());
+
+        let block_with = |block_chain: &ETHBlockchain, txns| {
+            ETHBlock::new(
+                block_chain.blocks.last().unwrap(),
+                alice,
+                0,
+                block_chain.next_base_fee(),
+                block_chain.next_gas_limit(),
+                txns,
+                vec![],
+            )
+        };
+        let repeat = block_with(&block_chain, vec![txn]);
+        assert!(!block_chain.add_block(repeat));
+        let other = signed_txn(&alice_key, 2, 1, bob, 0);
+        let twice = block_with(&block_chain, vec![other.clone(), other.clone()]);
+        assert!(!block_chain.add_block(twice));
+        let once = block_with(&block_chain, vec![other]);
+        assert!(block_chain.add_block(once));
+    }
+
+    #[test]
+    fn pool_orders_blocks_by_nonce_and_gasprice() {
+        let (alice_key, alice) = keypair(1);
+        let (bob_key, bob) = keypair(2);
+        let mut block_chain = ETHBlockchain::new(3, 0, alice);
+        let block = block_chain.flush_txns();
+        assert!(block_chain.add_block(block));
+
+        // fund Bob; one pending transaction isn't enough for a block
+        assert!(block_chain
+            .process_transaction(signed_txn(&alice_key, 1, 1, bob, 300))
+            .is_none());
+        let block = block_chain.flush_txns();
+        assert_eq!(block.get_transactions().len(), 1);
+        assert!(block_chain.add_block(block));
+
+        // stale nonces and unknown senders are turned away
+        assert!(block_chain
+            .process_transaction(signed_txn(&alice_key, 1, 1, bob, 0))
+            .is_none());
+        let (stranger_key, _) = keypair(3);
+        assert!(block_chain
+            .process_transaction(signed_txn(&stranger_key, 1, 9, bob, 0))
+            .is_none());
+        assert!(block_chain.get_pool().is_empty());
+
+        // Alice's nonce 3 waits for her nonce 2
+        assert!(block_chain
+            .process_transaction(signed_txn(&alice_key, 3, 1, bob, 0))
+            .is_none());
+        assert_eq!(block_chain.get_pool().queued_len(), 1);
+        assert!(block_chain
+            .process_transaction(signed_txn(&alice_key, 2, 1, bob, 0))
+            .is_none());
+        assert_eq!(block_chain.get_pool().pending_len(), 2);
+        let block = block_chain
+            .process_transaction(signed_txn(&bob_key, 1, 5, alice, 0))
+            .unwrap();
+
+        let order: Vec<(ETHAddress, u32)> = block
+            .get_transactions()
+            .iter()
+            .map(|txn| (txn.get_sender_addr().unwrap(), txn.nonce))
+            .collect();
+        assert_eq!(order, vec![(bob, 1), (alice, 2), (alice, 3)]);
+        assert!(block.get_receipts().iter().all(|r| r.outcome.is_ok()));
+        assert!(block_chain.get_pool().is_empty());
+    }
+
+    #[test]
+    fn replace_by_fee_changes_block_contents() {
+        let (alice_key, alice) = keypair(1);
+        let (_, bob) = keypair(2);
+        let mut block_chain = ETHBlockchain::new(2, 0, alice);
+        let block = block_chain.flush_txns();
+        assert!(block_chain.add_block(block));
+
+        // a payment to Bob, then cancelled by sending nothing to herself
+        let payment = signed_txn(&alice_key, 1, 10, bob, 300);
+        assert!(block_chain.process_transaction(payment).is_none());
+        let underpriced = signed_txn(&alice_key, 1, 10, alice, 0);
+        assert!(block_chain.process_transaction(underpriced).is_none());
+        assert_eq!(
+            block_chain.get_pool().get(&alice, 1).unwrap().recipient,
+            Some(bob)
+        );
+        let cancel = signed_txn(&alice_key, 1, 11, alice, 0);
+        assert!(block_chain.process_transaction(cancel).is_none());
+        assert_eq!(block_chain.get_pool().len(), 1);
+
+        let block = block_chain.flush_txns();
+        assert_eq!(block.get_transactions().len(), 1);
+        assert_eq!(block.get_transactions()[0].recipient, Some(alice));
+        assert_eq!(block.get_transactions()[0].gasprice, Wei::from_wei(11));
+        assert!(block.get_receipts()[0].outcome.is_ok());
+        assert!(block_chain.add_block(block));
+        assert!(block_chain.get_pool().is_empty());
+    }
+
+    #[test]
+    fn base_fee_converges_to_what_senders_pay() {
+        let (alice_key, alice) = keypair(1);
+        let mut block_chain = ETHBlockchain::new(4, 0, alice);
+        // three 5 gas transactions a block against a target of 10
+        block_chain.set_fee_market(FeeMarket::new(Wei::from_wei(10), 10));
+        let block = block_chain.flush_txns();
+        assert_eq!(block.get_base_fee(), Wei::from_wei(10));
+        assert!(block_chain.add_block(block));
+
+        let mut nonce = 0;
+        let mut base_fees = vec![];
+        for _ in 0..80 {
+            while block_chain.get_pool().pending_len() < 3 {
+                nonce += 1;
+                let mut txn = signed_txn(&alice_key, nonce, 40, alice, 0);
+                // [PUSH1(1), POP, STOP]
+                txn.code = vec![0x60, 1, 0x50, 0x00];
+                txn.sign_transaction(&alice_key);
+                assert!(block_chain.process_transaction(txn).is_none());
+            }
+            let block = block_chain.flush_txns();
+            base_fees.push(block.get_base_fee().get_wei());
+            assert!(block_chain.add_block(block));
+        }
+
+        // full blocks push the base fee up until nobody can pay it, and
+        // empty blocks bring it back down
+        assert!(base_fees[..10].windows(2).all(|w| w[0] < w[1]));
+        for &base_fee in &base_fees[40..] {
+            assert!((35..=45).contains(&base_fee), "base fee {}", base_fee);
+        }
+        assert!(block_chain.get_burned() > Wei::from_wei(0));
+
+        // a block has to carry the base fee its parent implies
+        let wrong = ETHBlock::new(
+            block_chain.blocks.last().unwrap(),
+            alice,
+            0,
+            block_chain.next_base_fee() + Wei::from_wei(1),
+            block_chain.next_gas_limit(),
+            vec![],
+            vec![],
+        );
+        assert!(!block_chain.add_block(wrong));
+    }
+
+    #[test]
+    fn blocks_fill_up_to_the_gas_limit() {
+        let (alice_key, alice) = keypair(1);
+        let (_, bob) = keypair(2);
+        let mut block_chain = ETHBlockchain::with_gas_limit(MIN_GAS_LIMIT, 0, alice);
+        let block = block_chain.flush_txns();
+        assert!(block_chain.add_block(block));
+
+        // [PUSH1(0), JUMP] loops until it runs out of gas, which is free
+        // at a gasprice of zero
+        let looping = |nonce| {
+            let mut txn = signed_txn(&alice_key, nonce, 0, bob, 0);
+            txn.gaslimit = 2000;
+            txn.code = vec![0x60, 0, 0x56];
+            txn.sign_transaction(&alice_key);
+            txn
+        };
+        assert!(block_chain.process_transaction(looping(1)).is_none());
+        assert!(block_chain.process_transaction(looping(2)).is_none());
+        // the third may use more gas than the block has left
+        let block = block_chain.process_transaction(looping(3)).unwrap();
+        assert_eq!(block.get_transactions().len(), 2);
+        assert!(block.get_gas_used() > 2 * 1900 && block.get_gas_used() <= 4000);
+        assert_eq!(block_chain.get_pool().pending_len(), 1);
+        assert!(block_chain.add_block(block));
+        let block = block_chain.flush_txns();
+        assert_eq!(block.get_transactions()[0].nonce, 3);
+        assert!(block_chain.add_block(block));
+
+        // nothing can ever fit a transaction asking for more than a block
+        let mut oversized = looping(4);
+        oversized.gaslimit = MIN_GAS_LIMIT + 1;
+        oversized.sign_transaction(&alice_key);
+        assert!(block_chain.process_transaction(oversized).is_none());
+        assert!(block_chain.get_pool().is_empty());
+
+        // a count cap still applies on top of the gas limit
+        block_chain.set_block_txn_limit(Some(1));
+        assert!(block_chain
+            .process_transaction(signed_txn(&alice_key, 4, 1, bob, 0))
+            .is_some());
+    }
+
+    #[test]
+    fn underpriced_transactions_dont_fill_blocks() {
+        let (alice_key, alice) = keypair(1);
+        let (bob_key, bob) = keypair(2);
+        let mut block_chain = ETHBlockchain::with_gas_limit(MIN_GAS_LIMIT, 0, alice);
+        let block = block_chain.flush_txns();
+        assert!(block_chain.add_block(block));
+        // Bob needs an account to send from
+        assert!(block_chain
+            .process_transaction(signed_txn(&alice_key, 1, 0, bob, 300))
+            .is_none());
+        let block = block_chain.flush_txns();
+        assert!(block_chain.add_block(block));
+        block_chain.set_fee_market(FeeMarket::new(Wei::from_wei(10), 10));
+        let block = block_chain.flush_txns();
+        assert!(block_chain.add_block(block));
+
+        let with_gaslimit = |key, nonce, gasprice| {
+            let mut txn = signed_txn(key, nonce, gasprice, alice, 0);
+            txn.gaslimit = 2500;
+            txn.sign_transaction(key);
+            txn
+        };
+        // more gas than a block has, none of it able to pay the base fee
+        for nonce in 1..=3 {
+            let txn = with_gaslimit(&bob_key, nonce, 1);
+            assert!(block_chain.process_transaction(txn).is_none());
+        }
+        assert!(block_chain
+            .process_transaction(with_gaslimit(&alice_key, 2, 20))
+            .is_none());
+        assert_eq!(block_chain.get_pool().pending_len(), 4);
+
+        // the transactions that can pay fill a block on their own
+        let block = block_chain
+            .process_transaction(with_gaslimit(&alice_key, 3, 20))
+            .unwrap();
+        assert_eq!(block.get_transactions().len(), 2);
+        assert_eq!(block_chain.get_pool().pending_len(), 3);
+    }
+
+    #[test]
+    fn gas_limit_follows_the_miners_target() {
+        let (_, alice) = keypair(1);
+        // each step is 1/1024 of the parent block's limit
+        let mut block_chain = ETHBlockchain::with_gas_limit(1 << 20, 0, alice);
+        block_chain.set_gas_limit_target((1 << 20) + 2500);
+        for expected in &[1024, 1024 + 1025, 2500, 2500] {
+            let block = block_chain.flush_txns();
+            assert_eq!(block.get_gas_limit(), (1 << 20) + expected);
+            assert!(block_chain.add_block(block));
+        }
+
+        // other miners' blocks can't jump further than a step either
+        let parent = block_chain.blocks.last().unwrap();
+        let jump = ETHBlock::new(
+            parent,
+            alice,
+            0,
+            Wei::from_wei(0),
+            (1 << 20) + 2500 + 2000,
+            vec![],
+            vec![],
+        );
+        assert!(!block_chain.add_block(jump));
+
+        // a genesis limit below the minimum still leaves a chain to build on
+        let mut block_chain = ETHBlockchain::with_gas_limit(100, 0, alice);
+        let block = block_chain.flush_txns();
+        assert_eq!(block.get_gas_limit(), MIN_GAS_LIMIT);
+        assert!(block_chain.add_block(block));
+    }
 }
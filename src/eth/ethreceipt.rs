@@ -0,0 +1,42 @@
+use super::aliases::ETHAddress;
+use super::bloom::Bloom;
+use super::evmstate::FailureReason;
+use super::gas::Gas;
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ETHLog {
+    pub address: ETHAddress,
+    pub topics: Vec<u8>,
+    pub data: u8,
+}
+
+impl ETHLog {
+    /// Returns true if the log was emitted by `address` and carries `topic`
+    /// in any position. A filter of `None` matches everything.
+    pub fn matches(&self, address: Option<&ETHAddress>, topic: Option<u8>) -> bool {
+        address.is_none_or(|addr| self.address == *addr)
+            && topic.is_none_or(|t| self.topics.contains(&t))
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ETHReceipt {
+    pub outcome: Result<(), FailureReason>,
+    pub gas_used: Gas,
+    pub logs: Vec<ETHLog>,
+    pub logs_bloom: Bloom,
+    // where a successful contract creation put the contract
+    pub contract_address: Option<ETHAddress>,
+}
+
+impl ETHReceipt {
+    pub fn new(outcome: Result<(), FailureReason>, gas_used: Gas, logs: Vec<ETHLog>) -> ETHReceipt {
+        ETHReceipt {
+            outcome,
+            gas_used,
+            logs_bloom: Bloom::from_logs(&logs),
+            logs,
+            contract_address: None,
+        }
+    }
+}
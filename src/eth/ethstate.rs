@@ -5,7 +5,21 @@ use super::ethaccount::ETHAccount;
 use super::ethtxn::ETHTxn;
 use super::wei::Wei;
 
-#[derive(Debug, Default)]
+/// The account operations transaction execution needs, so the same code
+/// can run against the real state or a throwaway view of it.
+pub trait StateAccess {
+    fn key_exists(&self, addr: &ETHAddress) -> bool;
+    fn get_value(&self, addr: &ETHAddress) -> Option<Wei>;
+    fn get_nonce(&self, addr: &ETHAddress) -> Option<u32>;
+    fn increment_nonce(&mut self, addr: &ETHAddress);
+    fn safe_deduct(&mut self, addr: &ETHAddress, amount: Wei) -> bool;
+    fn pay(&mut self, addr: &ETHAddress, amount: Wei);
+    fn get_code(&self, addr: &ETHAddress) -> Option<&[u8]>;
+    // creates the account if it doesn't exist
+    fn set_code(&mut self, addr: &ETHAddress, code: Vec<u8>);
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
 pub struct ETHState {
     accounts: HashMap<ETHAddress, ETHAccount>,
 }
@@ -45,4 +59,67 @@ impl ETHState {
     pub fn get_value(&self, addr: &ETHAddress) -> Option<Wei> {
         self.accounts.get(addr).map(|account| account.balance)
     }
+
+    pub fn get_nonce(&self, addr: &ETHAddress) -> Option<u32> {
+        self.accounts.get(addr).map(|account| account.nonce)
+    }
+
+    pub fn get_code(&self, addr: &ETHAddress) -> Option<&[u8]> {
+        self.accounts.get(addr).map(|account| &account.code[..])
+    }
+
+    pub fn set_code(&mut self, addr: &ETHAddress, code: Vec<u8>) {
+        self.accounts
+            .entry(*addr)
+            .or_insert_with(|| ETHAccount::new(*addr))
+            .code = code;
+    }
+
+    pub fn get_account(&self, addr: &ETHAddress) -> Option<&ETHAccount> {
+        self.accounts.get(addr)
+    }
+
+    // replaces the account with the same address, if there is one
+    pub fn set_account(&mut self, account: ETHAccount) {
+        self.accounts.insert(account.address, account);
+    }
+
+    // the sum of every account's balance
+    pub fn total_wei(&self) -> Wei {
+        self.accounts.values().map(|account| account.balance).sum()
+    }
+}
+
+impl StateAccess for ETHState {
+    fn key_exists(&self, addr: &ETHAddress) -> bool {
+        ETHState::key_exists(self, addr)
+    }
+
+    fn get_value(&self, addr: &ETHAddress) -> Option<Wei> {
+        ETHState::get_value(self, addr)
+    }
+
+    fn get_nonce(&self, addr: &ETHAddress) -> Option<u32> {
+        ETHState::get_nonce(self, addr)
+    }
+
+    fn increment_nonce(&mut self, addr: &ETHAddress) {
+        ETHState::increment_nonce(self, addr)
+    }
+
+    fn safe_deduct(&mut self, addr: &ETHAddress, amount: Wei) -> bool {
+        ETHState::safe_deduct(self, addr, amount)
+    }
+
+    fn pay(&mut self, addr: &ETHAddress, amount: Wei) {
+        ETHState::pay(self, addr, amount)
+    }
+
+    fn get_code(&self, addr: &ETHAddress) -> Option<&[u8]> {
+        ETHState::get_code(self, addr)
+    }
+
+    fn set_code(&mut self, addr: &ETHAddress, code: Vec<u8>) {
+        ETHState::set_code(self, addr, code)
+    }
 }
@@ -1,19 +1,48 @@
+use std::convert::TryFrom;
+
 use bincode::serialize;
 use sha3::Digest;
 
-use super::aliases::ETHAddress;
+use super::aliases::{ETHAddress, TxnHash};
 use super::gas::Gas;
+use super::rlp::{HashEncoding, Rlp};
 use super::wei::Wei;
 
+// the chain transactions are signed for unless configured otherwise
+pub const DEFAULT_CHAIN_ID: u32 = 1;
+
+// the EIP-2718 type of transactions with fee market caps
+const FEE_MARKET_TXN_TYPE: u8 = 2;
+// EIP-155 signatures have v = chain_id * 2 + 35 + recovery id
+const CHAIN_ID_V_OFFSET: u128 = 35;
+
+// half the secp256k1 curve order, rounded down. libsecp256k1 0.2's
+// `Scalar::is_high` counts this S as high, but Ethereum accepts it.
+const HALF_CURVE_ORDER: [u8; 32] = [
+    0x7f, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0x5d, 0x57, 0x6e, 0x73, 0x57, 0xa4, 0x50, 0x1d, 0xdf, 0xe9, 0x2f, 0x46, 0x68, 0x1b, 0x20, 0xa0,
+];
+
 #[derive(Debug, Serialize, Clone)]
 pub struct ETHTxn {
     pub nonce: u32,
+    // part of what's signed, so a transaction can't be replayed on another
+    // chain
+    pub chain_id: u32,
     pub gasprice: Wei,
+    // fee market caps on what's paid per unit of gas; see `get_max_fee`
+    pub max_fee: Option<Wei>,
+    pub max_priority_fee: Option<Wei>,
     pub gaslimit: Gas,
-    pub recipient: ETHAddress,
+    // None creates a contract, running `code` to get its runtime code
+    pub recipient: Option<ETHAddress>,
     pub value: Wei,
     pub code: Vec<u8>,
 
+    // what's signed and hashed: the bincode serialization or the RLP
+    // encoding
+    #[serde(skip_serializing)]
+    pub encoding: HashEncoding,
     #[serde(skip_serializing)]
     pub ecdsa_fields: (secp256k1::Signature, secp256k1::RecoveryId),
 }
@@ -24,6 +53,189 @@ impl ETHTxn {
         serialize(self).unwrap()
     }
 
+    /// The transaction as Ethereum encodes it: EIP-155 when only `gasprice`
+    /// is set, and EIP-1559 when either fee market cap is.
+    pub fn rlp_encoding(&self) -> Vec<u8> {
+        self.rlp(true)
+    }
+
+    fn rlp(&self, signed: bool) -> Vec<u8> {
+        let (signature, recovery_id) = &self.ecdsa_fields;
+        let recovery_id = u128::from(recovery_id.serialize());
+        let common = vec![
+            Rlp::uint(self.gaslimit),
+            // a creation's recipient is empty
+            Rlp::bytes(self.recipient.as_ref().map_or(&[][..], |addr| &addr[..])),
+            Rlp::uint(self.value.get_wei()),
+            Rlp::bytes(&self.code),
+        ];
+        let r = Rlp::uint_from_be(&signature.r.b32());
+        let s = Rlp::uint_from_be(&signature.s.b32());
+        let chain_id = u128::from(self.chain_id);
+
+        if self.max_fee.is_none() && self.max_priority_fee.is_none() {
+            let mut fields = vec![
+                Rlp::uint(u128::from(self.nonce)),
+                Rlp::uint(self.gasprice.get_wei()),
+            ];
+            fields.extend(common);
+            if signed {
+                let v = chain_id * 2 + CHAIN_ID_V_OFFSET + recovery_id;
+                fields.extend(vec![Rlp::uint(v), r, s]);
+            } else {
+                // EIP-155 signs the chain id in place of v, r and s
+                fields.extend(vec![Rlp::uint(chain_id), Rlp::uint(0), Rlp::uint(0)]);
+            }
+            return Rlp::List(fields).encode();
+        }
+
+        let mut fields = vec![
+            Rlp::uint(chain_id),
+            Rlp::uint(u128::from(self.nonce)),
+            Rlp::uint(self.get_max_priority_fee().get_wei()),
+            Rlp::uint(self.get_max_fee().get_wei()),
+        ];
+        fields.extend(common);
+        // an empty access list
+        fields.push(Rlp::List(vec![]));
+        if signed {
+            fields.extend(vec![Rlp::uint(recovery_id), r, s]);
+        }
+        let mut encoded = vec![FEE_MARKET_TXN_TYPE];
+        encoded.extend(Rlp::List(fields).encode());
+        encoded
+    }
+
+    /// Decodes what `rlp_encoding` produces. The transaction comes back with
+    /// the RLP encoding, so it hashes and verifies as it was sent.
+    pub fn from_rlp(encoded: &[u8]) -> Result<ETHTxn, String> {
+        let typed = encoded.first() == Some(&FEE_MARKET_TXN_TYPE);
+        let item = Rlp::decode(if typed { &encoded[1..] } else { encoded })?;
+        let fields = item.as_list().ok_or("a transaction is a list")?;
+        let expected = if typed { 12 } else { 9 };
+        if fields.len() != expected {
+            return Err(format!(
+                "expected {} fields, found {}",
+                expected,
+                fields.len()
+            ));
+        }
+        let uint = |index: usize| {
+            fields[index]
+                .as_uint()
+                .ok_or_else(|| format!("field {} isn't an integer", index))
+        };
+        let small = |index: usize| {
+            uint(index).and_then(|value| {
+                u32::try_from(value).map_err(|_| format!("field {} is too large", index))
+            })
+        };
+
+        // after the fees, both kinds are laid out the same
+        let rest = if typed { 4 } else { 2 };
+        let recipient = match fields[rest + 1].as_bytes() {
+            Some(&[]) => None,
+            Some(bytes) if bytes.len() == 20 => {
+                let mut addr: ETHAddress = [0; 20];
+                addr.copy_from_slice(bytes);
+                Some(addr)
+            }
+            _ => return Err("recipient isn't an address".to_string()),
+        };
+        let code = fields[rest + 3]
+            .as_bytes()
+            .ok_or("code isn't a byte string")?
+            .to_vec();
+        let mut signature = [0; 64];
+        for (half, item) in signature.chunks_mut(32).zip(&fields[expected - 2..]) {
+            let bytes = item
+                .as_bytes()
+                .filter(|bytes| bytes.len() <= 32 && bytes.first() != Some(&0))
+                .ok_or("signature isn't two 32 byte integers")?;
+            half[32 - bytes.len()..].copy_from_slice(bytes);
+        }
+        let mut txn = ETHTxn {
+            nonce: 0,
+            chain_id: 0,
+            gasprice: Wei::from_wei(0),
+            max_fee: None,
+            max_priority_fee: None,
+            gaslimit: uint(rest)?,
+            recipient,
+            value: Wei::from_wei(uint(rest + 2)?),
+            code,
+            encoding: HashEncoding::Rlp,
+            ecdsa_fields: (
+                secp256k1::Signature::parse(&signature),
+                secp256k1::RecoveryId::parse(0).unwrap(),
+            ),
+        };
+
+        let recovery_id = if typed {
+            if fields[8] != Rlp::List(vec![]) {
+                return Err("access lists aren't supported".to_string());
+            }
+            txn.chain_id = small(0)?;
+            txn.nonce = small(1)?;
+            txn.max_priority_fee = Some(Wei::from_wei(uint(2)?));
+            txn.max_fee = Some(Wei::from_wei(uint(3)?));
+            uint(9)?
+        } else {
+            txn.nonce = small(0)?;
+            txn.gasprice = Wei::from_wei(uint(1)?);
+            let v = uint(6)?
+                .checked_sub(CHAIN_ID_V_OFFSET)
+                .ok_or("signature isn't EIP-155")?;
+            txn.chain_id = u32::try_from(v / 2).map_err(|_| "chain id is too large")?;
+            v % 2
+        };
+        txn.ecdsa_fields.1 = u8::try_from(recovery_id)
+            .ok()
+            .and_then(|id| secp256k1::RecoveryId::parse(id).ok())
+            .ok_or("invalid recovery id")?;
+        Ok(txn)
+    }
+
+    /// What the signature is over, in the transaction's encoding.
+    pub fn signing_payload(&self) -> Vec<u8> {
+        match self.encoding {
+            HashEncoding::Bincode => self.binary_serialization(),
+            HashEncoding::Rlp => self.rlp(false),
+        }
+    }
+
+    /// Everything a transaction is sent as: the bincode serialization
+    /// followed by the signature and recovery id, or the RLP encoding.
+    pub fn signed_encoding(&self) -> Vec<u8> {
+        match self.encoding {
+            HashEncoding::Bincode => {
+                let (signature, recovery_id) = &self.ecdsa_fields;
+                let mut encoded = self.binary_serialization();
+                encoded.extend_from_slice(&signature.serialize());
+                encoded.push(recovery_id.serialize());
+                encoded
+            }
+            HashEncoding::Rlp => self.rlp_encoding(),
+        }
+    }
+
+    /// Identifies the transaction by its signed encoding. Only low-S
+    /// signatures are accepted, so a signed transaction can't be re-encoded
+    /// under a second hash.
+    pub fn hash(&self) -> TxnHash {
+        let mut hash: TxnHash = [0; 32];
+        hash.copy_from_slice(&sha3::Keccak256::digest(&self.signed_encoding()));
+        hash
+    }
+
+    /// Whether the signature's S is in the lower half of the curve order.
+    /// (r, n - s) is just as valid a signature, so only one of the pair is
+    /// accepted.
+    pub fn has_low_s(&self) -> bool {
+        let s = &self.ecdsa_fields.0.s;
+        !s.is_high() || s.b32() == HALF_CURVE_ORDER
+    }
+
     /// Converts the ETHTxn instance to raw bytes and then converts it to a
     /// secp256k1::Message.
     pub fn hashed_message(encoded: &[u8]) -> Result<secp256k1::Message, secp256k1::Error> {
@@ -31,9 +243,12 @@ impl ETHTxn {
         secp256k1::Message::parse_slice(&hash)
     }
 
-    /// Recovers the public key from the ETHTxn.
+    /// Recovers the public key from the ETHTxn. Fails for a high-S signature.
     pub fn recover_public_key(&self) -> Result<secp256k1::PublicKey, secp256k1::Error> {
-        let msg = self.binary_serialization();
+        if !self.has_low_s() {
+            return Err(secp256k1::Error::InvalidSignature);
+        }
+        let msg = self.signing_payload();
         let hashed_message = Self::hashed_message(&msg)?;
         let &(ref signature, ref recovery_id) = &self.ecdsa_fields;
         secp256k1::recover(&hashed_message, signature, recovery_id)
@@ -51,6 +266,37 @@ impl ETHTxn {
         Ok(sender_addr)
     }
 
+    /// Where a contract created by `sender`'s transaction with `nonce` goes:
+    /// the last 20 bytes of the Keccak hash of the RLP list [sender, nonce].
+    pub fn contract_address(sender: &ETHAddress, nonce: u32) -> ETHAddress {
+        let encoded = Rlp::List(vec![Rlp::bytes(sender), Rlp::uint(u128::from(nonce))]).encode();
+        let mut addr: ETHAddress = [0; 20];
+        addr.copy_from_slice(&sha3::Keccak256::digest(&encoded)[12..]);
+        addr
+    }
+
+    /// The most the sender pays per unit of gas, base fee included. A
+    /// legacy transaction sets only `gasprice`, which then caps both this
+    /// and the priority fee.
+    pub fn get_max_fee(&self) -> Wei {
+        self.max_fee.unwrap_or(self.gasprice)
+    }
+
+    /// The most the miner gets per unit of gas, on top of the base fee.
+    pub fn get_max_priority_fee(&self) -> Wei {
+        self.max_priority_fee.unwrap_or_else(|| self.get_max_fee())
+    }
+
+    /// What the sender pays per unit of gas in a block with `base_fee`, or
+    /// None if the transaction's max fee doesn't cover the base fee.
+    pub fn effective_gasprice(&self, base_fee: Wei) -> Option<Wei> {
+        let max_fee = self.get_max_fee();
+        if max_fee < base_fee {
+            return None;
+        }
+        Some(max_fee.min(base_fee.saturating_add(self.get_max_priority_fee())))
+    }
+
     /// Returns the sender's address.
     pub fn get_sender_addr(&self) -> Result<ETHAddress, secp256k1::Error> {
         let pub_key = self.recover_public_key()?;
@@ -58,11 +304,12 @@ impl ETHTxn {
     }
 
     pub fn sign_transaction(&mut self, sender_secret: &secp256k1::SecretKey) {
-        let msg = self.binary_serialization();
+        let msg = self.signing_payload();
         let msg = match ETHTxn::hashed_message(&msg) {
             Ok(val) => val,
             _ => panic!("Couldn't retrieve message"),
         };
+        // libsecp256k1 already returns the low-S form `has_low_s` requires
         self.ecdsa_fields = match secp256k1::sign(&msg, sender_secret) {
             Ok(val) => val,
             _ => panic!("Signature couldn't be generated"),
@@ -90,7 +337,10 @@ pub mod utils {
 
 #[cfg(test)]
 pub mod tests {
-    use super::{super::wei::Wei, utils::get_bs_ecsda_field, ETHTxn};
+    use super::{
+        super::hexutil, super::rlp::HashEncoding, super::rlp::Rlp, super::wei::Wei,
+        utils::get_bs_ecsda_field, ETHTxn, DEFAULT_CHAIN_ID,
+    };
 
     #[test]
     fn test_basic_crypto_should_pass() {
@@ -104,14 +354,18 @@ pub mod tests {
 
         let mut sample_txn = ETHTxn {
             nonce: 13,
+            chain_id: DEFAULT_CHAIN_ID,
             gasprice: Wei::from_wei(20),
+            max_fee: None,
+            max_priority_fee: None,
             gaslimit: 400,
             recipient: match ETHTxn::get_address_from_public_key(&receiver_pubkey) {
-                Ok(val) => val,
+                Ok(val) => Some(val),
                 _ => panic!("Address couldn't be generated"),
             },
             value: Wei::from_wei(10),
             code: vec![0x31, 0x3a, 0x56, 0x57, 0x50, 0x05],
+            encoding: HashEncoding::Bincode,
             ecdsa_fields: get_bs_ecsda_field(&secp256k1::SecretKey::random(&mut rng)),
         };
 
@@ -144,14 +398,18 @@ pub mod tests {
 
         let mut sample_txn = ETHTxn {
             nonce: 13,
+            chain_id: DEFAULT_CHAIN_ID,
             gasprice: Wei::from_wei(20),
+            max_fee: None,
+            max_priority_fee: None,
             gaslimit: 400,
             recipient: match ETHTxn::get_address_from_public_key(&receiver_pubkey) {
-                Ok(val) => val,
+                Ok(val) => Some(val),
                 _ => panic!("Address couldn't be generated"),
             },
             value: Wei::from_wei(10),
             code: vec![0x31, 0x3a, 0x56, 0x57, 0x50, 0x05],
+            encoding: HashEncoding::Bincode,
             ecdsa_fields: get_bs_ecsda_field(&secp256k1::SecretKey::random(&mut rng)),
         };
 
@@ -175,4 +433,228 @@ pub mod tests {
             ETHTxn::get_address_from_public_key(&random_pubkey).unwrap()
         );
     }
+
+    fn fixed_txn() -> ETHTxn {
+        let key = secp256k1::SecretKey::parse(&[7; 32]).unwrap();
+        let mut txn = ETHTxn {
+            nonce: 1,
+            chain_id: DEFAULT_CHAIN_ID,
+            gasprice: Wei::from_wei(20),
+            max_fee: None,
+            max_priority_fee: None,
+            gaslimit: 400,
+            recipient: Some([9; 20]),
+            value: Wei::from_wei(10),
+            code: vec![],
+            encoding: HashEncoding::Bincode,
+            ecdsa_fields: get_bs_ecsda_field(&key),
+        };
+        txn.sign_transaction(&key);
+        txn
+    }
+
+    // the same r with s = n - s' recovers the same key under the other
+    // recovery id
+    fn malleate(txn: &ETHTxn) -> ETHTxn {
+        let mut malleated = txn.clone();
+        let (ref mut signature, ref mut recovery_id) = malleated.ecdsa_fields;
+        signature.s = signature.s.neg();
+        *recovery_id = secp256k1::RecoveryId::parse(recovery_id.serialize() ^ 1).unwrap();
+        malleated
+    }
+
+    #[test]
+    fn high_s_signatures_rejected() {
+        let txn = fixed_txn();
+        assert!(txn.has_low_s());
+        let sender = txn.get_sender_addr().unwrap();
+
+        let malleated = malleate(&txn);
+        assert!(!malleated.has_low_s());
+        let msg = ETHTxn::hashed_message(&malleated.binary_serialization()).unwrap();
+        let (ref signature, ref recovery_id) = malleated.ecdsa_fields;
+        let pubkey = secp256k1::recover(&msg, signature, recovery_id).unwrap();
+        assert_eq!(
+            ETHTxn::get_address_from_public_key(&pubkey).unwrap(),
+            sender
+        );
+        assert!(malleated.get_sender_addr().is_err());
+        assert_ne!(malleated.hash(), txn.hash());
+
+        // the lowest high S, and the highest S there is, around half the
+        // curve order n = 0xfff...ebaaedce6af48a03bbfd25e8cd0364141
+        let vectors: [(&str, bool); 3] = [
+            (
+                "7fffffffffffffffffffffffffffffff5d576e7357a4501ddfe92f46681b20a0",
+                true,
+            ),
+            (
+                "7fffffffffffffffffffffffffffffff5d576e7357a4501ddfe92f46681b20a1",
+                false,
+            ),
+            (
+                "fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364140",
+                false,
+            ),
+        ];
+        for &(s, low) in &vectors {
+            let mut bytes = txn.ecdsa_fields.0.serialize();
+            for (i, byte) in bytes[32..].iter_mut().enumerate() {
+                *byte = u8::from_str_radix(&s[2 * i..2 * i + 2], 16).unwrap();
+            }
+            let mut vector = txn.clone();
+            vector.ecdsa_fields.0 = secp256k1::Signature::parse(&bytes);
+            assert_eq!(vector.has_low_s(), low, "s = {}", s);
+            if !low {
+                assert!(vector.get_sender_addr().is_err());
+            }
+        }
+    }
+
+    #[test]
+    fn hash_identifies_the_signed_transaction() {
+        let txn = fixed_txn();
+        assert_eq!(txn.hash(), txn.clone().hash());
+        assert_eq!(txn.hash(), fixed_txn().hash());
+
+        let mut other = txn.clone();
+        other.nonce += 1;
+        assert_ne!(other.hash(), txn.hash());
+        // the signature is part of what's hashed
+        other.nonce -= 1;
+        other.ecdsa_fields = get_bs_ecsda_field(&secp256k1::SecretKey::parse(&[7; 32]).unwrap());
+        assert_ne!(other.hash(), txn.hash());
+    }
+
+    // the example from EIP-155
+    #[test]
+    fn eip155_example() {
+        let key = secp256k1::SecretKey::parse(&[0x46; 32]).unwrap();
+        let mut txn = ETHTxn {
+            nonce: 9,
+            chain_id: 1,
+            gasprice: Wei::from_gwei(20),
+            max_fee: None,
+            max_priority_fee: None,
+            gaslimit: 21000,
+            recipient: Some([0x35; 20]),
+            value: Wei::from_eth(1),
+            code: vec![],
+            encoding: HashEncoding::Rlp,
+            ecdsa_fields: get_bs_ecsda_field(&key),
+        };
+        assert_eq!(
+            hexutil::encode(&txn.signing_payload()),
+            "ec098504a817c800825208943535353535353535353535353535353535353535880de0b6b3a7640000\
+             80018080"
+        );
+        txn.sign_transaction(&key);
+        assert_eq!(
+            hexutil::encode(&txn.signed_encoding()),
+            "f86c098504a817c800825208943535353535353535353535353535353535353535880de0b6b3a7640000\
+             8025a028ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276a067cbe9d8997f\
+             761aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83"
+        );
+        assert_eq!(
+            hexutil::encode(&txn.hash()),
+            "33469b22e9f636356c4160a87eb19df52b7412e8eac32a4a55ffe88ea8350788"
+        );
+        assert_eq!(
+            hexutil::encode(&txn.get_sender_addr().unwrap()),
+            "9d8a62f656a8d1615c1294fd71e9cfb3e4855a4f"
+        );
+
+        // signed over bincode, the signature means nothing under RLP
+        txn.encoding = HashEncoding::Bincode;
+        txn.sign_transaction(&key);
+        txn.encoding = HashEncoding::Rlp;
+        assert_ne!(
+            hexutil::encode(&txn.get_sender_addr().unwrap()),
+            "9d8a62f656a8d1615c1294fd71e9cfb3e4855a4f"
+        );
+    }
+
+    #[test]
+    fn fee_market_txns_are_typed() {
+        let key = secp256k1::SecretKey::parse(&[7; 32]).unwrap();
+        let mut txn = fixed_txn();
+        txn.encoding = HashEncoding::Rlp;
+        txn.max_fee = Some(Wei::from_wei(30));
+        txn.max_priority_fee = Some(Wei::from_wei(2));
+        txn.sign_transaction(&key);
+        assert_eq!(
+            txn.get_sender_addr().unwrap(),
+            fixed_txn().get_sender_addr().unwrap()
+        );
+
+        let encoded = txn.signed_encoding();
+        assert_eq!(encoded[0], 2);
+        let decoded = Rlp::decode(&encoded[1..]).unwrap();
+        let fields = decoded.as_list().unwrap();
+        assert_eq!(fields.len(), 12);
+        let uints: Vec<Option<u128>> = fields.iter().map(Rlp::as_uint).collect();
+        assert_eq!(
+            &uints[..5],
+            &[Some(1), Some(1), Some(2), Some(30), Some(400)]
+        );
+        assert_eq!(fields[5].as_bytes(), Some(&[9; 20][..]));
+        assert_eq!(fields[8], Rlp::List(vec![]));
+        assert_eq!(fields[10], Rlp::uint_from_be(&txn.ecdsa_fields.0.r.b32()));
+        assert_eq!(txn.signing_payload()[0], 2);
+    }
+
+    #[test]
+    fn rlp_transactions_decode() {
+        let key = secp256k1::SecretKey::parse(&[7; 32]).unwrap();
+        let mut legacy = fixed_txn();
+        legacy.encoding = HashEncoding::Rlp;
+        legacy.chain_id = 300;
+        legacy.code = vec![0x60, 1, 0x00];
+        legacy.sign_transaction(&key);
+        let mut typed = legacy.clone();
+        typed.recipient = None;
+        typed.max_fee = Some(Wei::from_wei(30));
+        typed.max_priority_fee = Some(Wei::from_wei(2));
+        typed.sign_transaction(&key);
+
+        for txn in &[legacy, typed] {
+            let decoded = ETHTxn::from_rlp(&txn.signed_encoding()).unwrap();
+            assert_eq!(decoded.hash(), txn.hash());
+            assert_eq!(decoded.get_sender_addr(), txn.get_sender_addr());
+            assert_eq!(decoded.chain_id, 300);
+            assert_eq!(decoded.code, txn.code);
+            assert_eq!(decoded.recipient, txn.recipient);
+        }
+
+        // a pre-EIP-155 v, a missing field, and a list that isn't one
+        let mut fields = vec![Rlp::uint(1); 9];
+        fields[6] = Rlp::uint(27);
+        assert!(ETHTxn::from_rlp(&Rlp::List(fields.clone()).encode()).is_err());
+        fields[6] = Rlp::uint(37);
+        fields.pop();
+        assert!(ETHTxn::from_rlp(&Rlp::List(fields).encode()).is_err());
+        assert!(ETHTxn::from_rlp(&Rlp::bytes(b"dog").encode()).is_err());
+    }
+
+    #[test]
+    fn contract_addresses() {
+        let sender = hexutil::decode("6ac7ea33f8831ea9dcc53393aaa88b25a785dbf0").unwrap();
+        let mut sender_addr = [0; 20];
+        sender_addr.copy_from_slice(&sender);
+        assert_eq!(
+            hexutil::encode(&ETHTxn::contract_address(&sender_addr, 0)),
+            "cd234a471b72ba2f1ccf0a70fcaba648a5eecd8d"
+        );
+        assert_eq!(
+            hexutil::encode(&ETHTxn::contract_address(&sender_addr, 1)),
+            "343c43a37d37dff08ae8c4a11544c718abb4fcf8"
+        );
+
+        // a creation has an empty recipient field
+        let mut txn = fixed_txn();
+        txn.encoding = HashEncoding::Rlp;
+        txn.recipient = None;
+        let decoded = Rlp::decode(&txn.signing_payload()).unwrap();
+        assert_eq!(decoded.as_list().unwrap()[3], Rlp::bytes(&[]));
+    }
 }
@@ -1,39 +1,128 @@
+use std::sync::Arc;
+
 use super::gas::Gas;
+use super::gasschedule::GasSchedule;
 use super::ops::Op;
+use super::tracer::{NoopTracer, StepInfo, Tracer};
 use super::wei::Wei;
 
-pub struct ExecutionContext {
+pub const MEMORY_SIZE: usize = 256;
+
+pub struct ExecutionContext<T: Tracer = NoopTracer> {
     stack: Vec<u8>,
+    // byte-addressed scratch memory, one byte per stack-sized address
+    memory: [u8; MEMORY_SIZE],
     pc: u32,
+    // excludes gas prepaid for the rest of the current straight-line run
     gas_left: Gas,
+    prepaid: Gas,
     code: Vec<Op>,
+    // run_costs[i] is the gas for ops i.. up to the end of i's straight-line run
+    run_costs: Vec<Gas>,
+    block_gas: bool,
     txn_value: Wei,
+    logs: Vec<LogEntry>,
+    // the memory RETURN handed back
+    output: Vec<u8>,
+    schedule: Arc<GasSchedule>,
+    tracer: T,
+}
+
+// a log emitted by executing code; the emitting address is attached later
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct LogEntry {
+    pub topics: Vec<u8>,
+    pub data: u8,
 }
 
-enum OperationResult {
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum OperationResult {
     Continue,
     Stop,
 }
 
 impl ExecutionContext {
     pub fn new(gaslimit: Gas, code: Vec<Op>, txn_value: Wei) -> ExecutionContext {
+        ExecutionContext::with_tracer(gaslimit, code, txn_value, NoopTracer)
+    }
+}
+
+impl<T: Tracer> ExecutionContext<T> {
+    pub fn with_tracer(gaslimit: Gas, code: Vec<Op>, txn_value: Wei, tracer: T) -> Self {
+        let schedule = GasSchedule::shared_default();
         ExecutionContext {
             stack: Vec::new(),
+            memory: [0; MEMORY_SIZE],
             pc: 0,
             gas_left: gaslimit,
+            prepaid: 0,
+            run_costs: run_costs(&code, &schedule),
+            block_gas: true,
             code,
             txn_value,
+            logs: Vec::new(),
+            output: Vec::new(),
+            schedule,
+            tracer,
         }
     }
 
+    pub fn set_gas_schedule(&mut self, schedule: Arc<GasSchedule>) {
+        self.refund_prepaid();
+        self.run_costs = run_costs(&self.code, &schedule);
+        self.schedule = schedule;
+    }
+
+    // Charging a whole straight-line run up front skips the per-op gas
+    // check. It's on by default; turning it off charges every op as it runs.
+    // Either way the gas used and the point of failure are the same.
+    pub fn set_block_gas_charging(&mut self, enabled: bool) {
+        self.refund_prepaid();
+        self.block_gas = enabled;
+    }
+
+    pub fn into_tracer(self) -> T {
+        self.tracer
+    }
+
     pub fn get_gas_left(&self) -> Gas {
-        self.gas_left
+        self.gas_left + self.prepaid
     }
 
     pub fn get_value(&self) -> Wei {
         self.txn_value
     }
 
+    pub fn get_logs(&self) -> &[LogEntry] {
+        &self.logs
+    }
+
+    pub fn get_output(&self) -> &[u8] {
+        &self.output
+    }
+
+    pub fn get_pc(&self) -> u32 {
+        self.pc
+    }
+
+    pub fn get_stack(&self) -> &[u8] {
+        &self.stack
+    }
+
+    pub fn get_memory(&self) -> &[u8] {
+        &self.memory
+    }
+
+    pub fn get_code(&self) -> &[Op] {
+        &self.code
+    }
+
+    // execute a single op; Err means the code terminated abnormally
+    #[allow(clippy::result_unit_err)]
+    pub fn step(&mut self) -> Result<OperationResult, ()> {
+        self.execute_cycle()
+    }
+
     // return true if terminated normally, false on error
     pub fn finish_executing(&mut self) -> bool {
         while let Ok(result) = self.execute_cycle() {
@@ -53,6 +142,22 @@ impl ExecutionContext {
         self.stack.push(b);
     }
 
+    // pops the data byte followed by `num_topics` topics
+    fn log(&mut self, num_topics: usize) -> Result<(), ()> {
+        let data = self.pop()?;
+        let mut topics = Vec::with_capacity(num_topics);
+        for _ in 0..num_topics {
+            topics.push(self.pop()?);
+        }
+        self.logs.push(LogEntry { topics, data });
+        Ok(())
+    }
+
+    fn refund_prepaid(&mut self) {
+        self.gas_left += self.prepaid;
+        self.prepaid = 0;
+    }
+
     fn execute_cycle(&mut self) -> Result<OperationResult, ()> {
         if self.code.is_empty() {
             return Ok(OperationResult::Stop);
@@ -61,20 +166,58 @@ impl ExecutionContext {
             // pc out of bounds
             return Err(());
         }
+        let op = self.code[self.pc as usize];
+        if self.block_gas && self.prepaid == 0 {
+            // entering a run; if it can't be paid for in full, the ops
+            // are charged one at a time so we fail at the same op
+            let cost = self.run_costs[self.pc as usize];
+            if cost <= self.gas_left {
+                self.gas_left -= cost;
+                self.prepaid = cost;
+            }
+        }
+        self.tracer.before_op(&StepInfo {
+            pc: self.pc,
+            op,
+            gas_cost: self.schedule.cost(op),
+            stack: &self.stack,
+            gas_left: self.get_gas_left(),
+            value: self.txn_value,
+        });
+        let result = self.execute_op(op);
+        if result != Ok(OperationResult::Continue) {
+            // the failing op and anything after it weren't run; STOP
+            // isn't charged either
+            self.refund_prepaid();
+        }
+        self.tracer.after_op(
+            &StepInfo {
+                pc: self.pc,
+                op,
+                gas_cost: self.schedule.cost(op),
+                stack: &self.stack,
+                gas_left: self.get_gas_left(),
+                value: self.txn_value,
+            },
+            result.is_ok(),
+        );
+        result
+    }
+
+    fn execute_op(&mut self, op: Op) -> Result<OperationResult, ()> {
         // default pc increment
         let mut new_pc = self.pc + 1;
-        let op = self.code[self.pc as usize];
         match op {
             Op::STOP => return Ok(OperationResult::Stop),
             Op::ADD => {
                 let a = self.pop()?;
                 let b = self.pop()?;
-                self.push(a + b);
+                self.push(a.wrapping_add(b));
             }
             Op::MUL => {
                 let a = self.pop()?;
                 let b = self.pop()?;
-                self.push(a * b);
+                self.push(a.wrapping_mul(b));
             }
             Op::SUB => {
                 let a = self.pop()?;
@@ -130,6 +273,16 @@ impl ExecutionContext {
             Op::POP => {
                 self.pop()?;
             }
+            Op::MLOAD => {
+                let addr = self.pop()?;
+                let val = self.memory[usize::from(addr)];
+                self.push(val);
+            }
+            Op::MSTORE => {
+                let addr = self.pop()?;
+                let val = self.pop()?;
+                self.memory[usize::from(addr)] = val;
+            }
             Op::JUMP => {
                 new_pc = u32::from(self.pop()?);
             }
@@ -149,7 +302,8 @@ impl ExecutionContext {
             }
             Op::ADDVAL => {
                 let a = self.pop()?;
-                self.txn_value += Wei::from_wei(a.into());
+                // saturates, like SUBVAL
+                self.txn_value = self.txn_value.saturating_add(Wei::from_wei(a.into()));
             }
             Op::SUBVAL => {
                 let a = self.pop()?;
@@ -159,20 +313,56 @@ impl ExecutionContext {
                     None => Wei::from_wei(0),
                 };
             }
+            Op::LOG0 | Op::LOG1 | Op::LOG2 | Op::LOG3 | Op::LOG4 => {
+                self.log(op.log_topics())?;
+            }
+            Op::RETURN => {
+                let offset = usize::from(self.pop()?);
+                let len = usize::from(self.pop()?);
+                self.output = self.memory.get(offset..offset + len).ok_or(())?.to_vec();
+                return Ok(OperationResult::Stop);
+            }
             Op::INVALID(_) => return Err(()),
         };
         self.pc = new_pc;
-        self.gas_left = match self.gas_left.checked_sub(op.to_cost()) {
-            Some(val) => val,
-            None => return Err(()),
-        };
+        let cost = self.schedule.cost(op);
+        if self.prepaid > 0 {
+            // the run's prepaid gas always covers its remaining ops
+            self.prepaid -= cost;
+        } else {
+            self.gas_left = match self.gas_left.checked_sub(cost) {
+                Some(val) => val,
+                None => return Err(()),
+            };
+        }
         Ok(OperationResult::Continue)
     }
 }
 
+fn run_costs(code: &[Op], schedule: &GasSchedule) -> Vec<Gas> {
+    let mut costs = vec![0; code.len()];
+    let mut rest = 0;
+    for (i, op) in code.iter().enumerate().rev() {
+        if let Op::JUMP | Op::JUMPI | Op::STOP | Op::RETURN | Op::INVALID(_) = op {
+            // control may leave the run here
+            rest = 0;
+        }
+        rest += schedule.cost(*op);
+        costs[i] = rest;
+    }
+    costs
+}
+
 #[cfg(test)]
 mod tests {
-    use super::{super::ops::Op::*, super::wei::Wei, ExecutionContext};
+    use std::sync::Arc;
+
+    use rand::Rng;
+
+    use super::{
+        super::gas::Gas, super::gasschedule::GasSchedule, super::ops::Op, super::ops::Op::*,
+        super::wei::Wei, ExecutionContext, LogEntry,
+    };
 
     #[test]
     fn basic_evmexec_execution() {
@@ -233,4 +423,153 @@ mod tests {
         let mut engine = ExecutionContext::new(1000, ops, Wei::from_wei(4));
         assert!(!engine.finish_executing());
     }
+
+    #[test]
+    fn evmexec_custom_gas_schedule() {
+        let schedule = Arc::new(GasSchedule::default().with_cost(ADDVAL, 40));
+        let ops = vec![PUSH1(1), ADDVAL, STOP];
+        let mut engine = ExecutionContext::new(45, ops.clone(), Wei::from_wei(0));
+        engine.set_gas_schedule(schedule.clone());
+        assert!(engine.finish_executing());
+        assert_eq!(engine.get_gas_left(), 45 - 3 - 40);
+
+        let mut engine = ExecutionContext::new(42, ops, Wei::from_wei(0));
+        engine.set_gas_schedule(schedule);
+        assert!(!engine.finish_executing());
+    }
+
+    #[test]
+    fn evmexec_return() {
+        // stores [7, 8] at 10 and returns it, with nothing after running
+        let ops = vec![
+            PUSH1(7),
+            PUSH1(10),
+            MSTORE,
+            PUSH1(8),
+            PUSH1(11),
+            MSTORE,
+            PUSH1(2),
+            PUSH1(10),
+            RETURN,
+            INVALID(0xff),
+        ];
+        let mut engine = ExecutionContext::new(1000, ops, Wei::from_wei(0));
+        assert!(engine.finish_executing());
+        assert_eq!(engine.get_output(), &[7, 8]);
+        assert!(engine.get_stack().is_empty());
+    }
+
+    #[test]
+    fn evmexec_logs() {
+        let ops = vec![PUSH1(5), PUSH1(4), PUSH1(3), LOG2, PUSH1(9), LOG0, STOP];
+        let gascost = ops.iter().fold(0, |sum, x| sum + x.to_cost());
+        let mut engine = ExecutionContext::new(gascost, ops, Wei::from_wei(0));
+        assert!(engine.finish_executing());
+        assert_eq!(
+            engine.get_logs(),
+            &[
+                LogEntry {
+                    topics: vec![4, 5],
+                    data: 3,
+                },
+                LogEntry {
+                    topics: vec![],
+                    data: 9,
+                },
+            ]
+        );
+
+        let mut engine = ExecutionContext::new(1000, vec![PUSH1(1), LOG1, STOP], Wei::from_wei(0));
+        assert!(!engine.finish_executing());
+    }
+
+    #[test]
+    fn evmexec_arithmetic_wraps() {
+        let ops = vec![PUSH1(100), PUSH1(200), ADD, PUSH1(16), PUSH1(16), MUL, STOP];
+        let mut engine = ExecutionContext::new(1000, ops, Wei::from_wei(0));
+        assert!(engine.finish_executing());
+        assert_eq!(engine.get_stack(), &[44, 0]);
+    }
+
+    #[test]
+    fn evmexec_memory() {
+        // mem[9] = 4; push mem[9] * 3; mem[200] is still zero
+        let ops = vec![
+            PUSH1(4),
+            PUSH1(9),
+            MSTORE,
+            PUSH1(3),
+            PUSH1(9),
+            MLOAD,
+            MUL,
+            SETVAL,
+            PUSH1(200),
+            MLOAD,
+            STOP,
+        ];
+        let mut engine = ExecutionContext::new(1000, ops, Wei::from_wei(0));
+        assert!(engine.finish_executing());
+        assert_eq!(engine.get_value(), Wei::from_wei(12));
+        assert_eq!(engine.get_memory()[9], 4);
+        assert_eq!(engine.get_stack(), &[0]);
+    }
+
+    #[test]
+    fn evmexec_block_gas_matches_per_op() {
+        let pool = [
+            STOP,
+            ADD,
+            MUL,
+            SUB,
+            DIV,
+            LT,
+            GT,
+            EQ,
+            ISZERO,
+            POP,
+            MLOAD,
+            MSTORE,
+            JUMP,
+            JUMPI,
+            SETVAL,
+            ADDVAL,
+            SUBVAL,
+            LOG0,
+            LOG1,
+            INVALID(0x0c),
+        ];
+        let mut rng = rand::thread_rng();
+        for _ in 0..2000 {
+            let len = rng.gen_range(1, 24);
+            let code: Vec<Op> = (0..len)
+                .map(|_| {
+                    if rng.gen_range(0, 2) == 0 {
+                        PUSH1(rng.gen_range(0, len as u8 + 2))
+                    } else {
+                        pool[rng.gen_range(0, pool.len())]
+                    }
+                })
+                .collect();
+            let gaslimit = Gas::from(rng.gen_range(0u32, 1200));
+            let run = |block_gas: bool| {
+                let mut engine = ExecutionContext::new(gaslimit, code.clone(), Wei::from_wei(5));
+                engine.set_block_gas_charging(block_gas);
+                let mut steps = 0;
+                let mut result = Ok(super::OperationResult::Continue);
+                while result == Ok(super::OperationResult::Continue) && steps < 500 {
+                    result = engine.step();
+                    steps += 1;
+                }
+                (
+                    result,
+                    engine.get_gas_left(),
+                    engine.get_pc(),
+                    engine.get_stack().to_vec(),
+                    engine.get_value(),
+                    engine.get_logs().to_vec(),
+                )
+            };
+            assert_eq!(run(true), run(false), "code {:?} gas {}", code, gaslimit);
+        }
+    }
 }
@@ -1,33 +1,156 @@
-use super::aliases::ETHAddress;
-use super::ethstate::ETHState;
-use super::ethtxn::ETHTxn;
-use super::evmexec::ExecutionContext;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use super::aliases::{ETHAddress, TxnHash};
+use super::blockstm;
+use super::ethreceipt::{ETHLog, ETHReceipt};
+use super::ethstate::{ETHState, StateAccess};
+use super::ethtxn::{ETHTxn, DEFAULT_CHAIN_ID};
+use super::gas::Gas;
+use super::gasschedule::GasSchedule;
+use super::interpreter::{ExecutionResult, Interpreter, MatchInterpreter};
 use super::ops::Op;
+use super::overlay::StateOverlay;
+use super::rlp::HashEncoding;
+use super::senders;
 use super::wei::Wei;
 
-const MINING_REWARD: u128 = 1000; // in wei
+pub(crate) const MINING_REWARD: u128 = 1000; // in wei
+const ESTIMATE_GAS_CAP: Gas = 10_000_000;
 
 pub struct EVMState {
     world_state: ETHState,
+    env: ExecEnv,
+    // base fees taken out of circulation so far
+    burned: Wei,
+    // senders recovered ahead of running their transactions, None if the
+    // signature recovered to nothing
+    senders: HashMap<TxnHash, Option<ETHAddress>>,
+}
+
+// everything about running a transaction that isn't account state
+#[derive(Clone)]
+struct ExecEnv {
     miner_address: ETHAddress,
+    gas_schedule: Arc<GasSchedule>,
+    interpreter: Arc<dyn Interpreter + Send + Sync>,
+    base_fee: Wei,
+    // transactions signed for any other chain are rejected
+    chain_id: u32,
+    // transactions signed over any other encoding are rejected
+    encoding: HashEncoding,
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum FailureReason {
     InvalidSignature,
     InvalidNonce,
     InsufficientBalance,
     InvalidCode,
+    // gaslimit * gasprice doesn't fit in Wei
+    GasLimitTooHigh,
+    // the transaction's max fee is below the block's base fee
+    FeeBelowBaseFee,
+    // signed for a different chain
+    WrongChainId,
+    // signed over a different encoding than the chain's
+    WrongEncoding,
+    // a contract already exists where a creation would put its contract
+    AddressCollision,
+}
+
+impl FailureReason {
+    /// Whether the transaction was turned away before it ran, leaving its
+    /// nonce unused and nothing charged. Blocks leave these out.
+    pub fn is_rejection(&self) -> bool {
+        matches!(
+            self,
+            FailureReason::InvalidSignature
+                | FailureReason::InvalidNonce
+                | FailureReason::GasLimitTooHigh
+                | FailureReason::FeeBelowBaseFee
+                | FailureReason::WrongChainId
+                | FailureReason::WrongEncoding
+        )
+    }
+}
+
+/// The result of running a transaction without committing it.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Simulation {
+    pub outcome: Result<(), FailureReason>,
+    pub gas_used: Gas,
+    // the transaction value after the code ran
+    pub value: Wei,
+    pub logs: Vec<ETHLog>,
+}
+
+// what running a transaction's code produced, kept even if it then failed
+#[derive(Default)]
+struct Effects {
+    gas_used: Gas,
+    burned: Wei,
+    value: Wei,
+    logs: Vec<ETHLog>,
+    contract_address: Option<ETHAddress>,
 }
 
 impl EVMState {
     pub fn new(state: ETHState, miner: ETHAddress) -> EVMState {
         EVMState {
             world_state: state,
-            miner_address: miner,
+            env: ExecEnv {
+                miner_address: miner,
+                gas_schedule: GasSchedule::shared_default(),
+                interpreter: Arc::new(MatchInterpreter),
+                base_fee: Wei::from_wei(0),
+                chain_id: DEFAULT_CHAIN_ID,
+                encoding: HashEncoding::Bincode,
+            },
+            burned: Wei::from_wei(0),
+            senders: HashMap::new(),
         }
     }
 
+    // the schedule charged by transactions run from now on
+    pub fn set_gas_schedule(&mut self, schedule: Arc<GasSchedule>) {
+        self.env.gas_schedule = schedule;
+    }
+
+    // the backend that runs transaction code; every backend gives the same results
+    pub fn set_interpreter(&mut self, interpreter: Arc<dyn Interpreter + Send + Sync>) {
+        self.env.interpreter = interpreter;
+    }
+
+    // burned from every unit of gas used from now on; zero disables burning
+    pub fn set_base_fee(&mut self, base_fee: Wei) {
+        self.env.base_fee = base_fee;
+    }
+
+    pub fn set_chain_id(&mut self, chain_id: u32) {
+        self.env.chain_id = chain_id;
+    }
+
+    pub fn get_chain_id(&self) -> u32 {
+        self.env.chain_id
+    }
+
+    pub fn set_encoding(&mut self, encoding: HashEncoding) {
+        self.env.encoding = encoding;
+    }
+
+    pub fn get_encoding(&self) -> HashEncoding {
+        self.env.encoding
+    }
+
+    pub fn get_burned(&self) -> Wei {
+        self.burned
+    }
+
+    pub fn get_world_state(&self) -> &ETHState {
+        &self.world_state
+    }
+
     pub fn get_final_state(self) -> ETHState {
         self.world_state
     }
@@ -38,72 +161,301 @@ impl EVMState {
 
     // ingest and process a well-formed transaction
     pub fn run_transaction(&mut self, txn: &ETHTxn) -> Result<(), FailureReason> {
-        // make sure sender's address exists and transaction is correctly signed
-        let sender_addr = match txn.get_sender_addr() {
-            Ok(addr) => {
-                if self.world_state.key_exists(&addr) {
-                    addr
-                } else {
-                    return Err(FailureReason::InvalidSignature);
-                }
+        self.execute_transaction(txn).outcome
+    }
+
+    // like run_transaction, but also reports gas usage and emitted logs
+    pub fn execute_transaction(&mut self, txn: &ETHTxn) -> ETHReceipt {
+        let mut effects = Effects::default();
+        let outcome = self.apply_transaction(txn, &mut effects);
+        self.receipt(outcome, effects)
+    }
+
+    /// Recovers the senders of `txns` across `num_threads` threads, so
+    /// running them afterwards doesn't have to.
+    pub fn cache_senders(&mut self, txns: &[ETHTxn], num_threads: usize) {
+        let recovered = senders::recover_senders(txns, num_threads);
+        self.senders
+            .extend(txns.iter().map(ETHTxn::hash).zip(recovered));
+    }
+
+    /// Runs a block's transactions in order, once all their senders have
+    /// been recovered in parallel.
+    pub fn execute_block(&mut self, txns: &[ETHTxn], num_threads: usize) -> Vec<ETHReceipt> {
+        self.cache_senders(txns, num_threads);
+        let receipts = txns
+            .iter()
+            .map(|txn| self.execute_transaction(txn))
+            .collect();
+        // left behind by transactions rejected before their sender was needed
+        self.senders.clear();
+        receipts
+    }
+
+    /// Like `execute_block`, but runs the transactions speculatively across
+    /// `num_threads` threads, running one again whenever an earlier one
+    /// wrote an account it read. The state and receipts are the same as
+    /// running them one after another.
+    pub fn execute_block_parallel(
+        &mut self,
+        txns: &[ETHTxn],
+        num_threads: usize,
+    ) -> Vec<ETHReceipt> {
+        let senders = senders::recover_senders(txns, num_threads);
+        let env = &self.env;
+        let outcomes = blockstm::execute(
+            &mut self.world_state,
+            txns.len(),
+            num_threads,
+            |index, state| {
+                let mut effects = Effects::default();
+                let outcome = env
+                    .check_signed_for(&txns[index])
+                    .and_then(|()| env.apply(state, senders[index], &txns[index], &mut effects));
+                (outcome, effects)
+            },
+        );
+        outcomes
+            .into_iter()
+            .map(|(outcome, effects)| self.receipt(outcome, effects))
+            .collect()
+    }
+
+    /// Runs `txn` as if it were sent by `from`, against a copy-on-write view
+    /// of the state that's thrown away afterwards. The signature and nonce
+    /// aren't checked, so unsigned transactions can be simulated.
+    pub fn simulate_transaction(&self, txn: &ETHTxn, from: &ETHAddress) -> Simulation {
+        let mut overlay = StateOverlay::new(&self.world_state);
+        let mut effects = Effects::default();
+        let outcome = self.env.execute(&mut overlay, from, txn, &mut effects);
+        if outcome.is_err() {
+            effects.logs.clear();
+        }
+        Simulation {
+            outcome,
+            gas_used: effects.gas_used,
+            value: effects.value,
+            logs: effects.logs,
+        }
+    }
+
+    /// Finds the smallest gas limit `txn` succeeds with when sent by `from`.
+    /// The search is capped by what `from` can afford at the price `txn`
+    /// pays per unit of gas.
+    pub fn estimate_gas(&self, txn: &ETHTxn, from: &ETHAddress) -> Result<Gas, FailureReason> {
+        let mut txn = txn.clone();
+        let mut hi = ESTIMATE_GAS_CAP;
+        let gasprice = txn
+            .effective_gasprice(self.env.base_fee)
+            .ok_or(FailureReason::FeeBelowBaseFee)?;
+        let balance = self.world_state.get_value(from).unwrap_or_default();
+        if let Some(affordable) = balance / gasprice {
+            hi = hi.min(affordable);
+        }
+        txn.gaslimit = hi;
+        let simulation = self.simulate_transaction(&txn, from);
+        simulation.outcome?;
+
+        // no limit below what the successful run used can succeed
+        let mut lo = simulation.gas_used;
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            txn.gaslimit = mid;
+            if self.simulate_transaction(&txn, from).outcome.is_ok() {
+                hi = mid;
+            } else {
+                lo = mid + 1;
+            }
+        }
+        Ok(hi)
+    }
+
+    fn apply_transaction(
+        &mut self,
+        txn: &ETHTxn,
+        effects: &mut Effects,
+    ) -> Result<(), FailureReason> {
+        // checked before the sender, which is expensive to recover
+        self.env.check_signed_for(txn)?;
+        let sender = self.sender_of(txn);
+        self.env.apply(&mut self.world_state, sender, txn, effects)
+    }
+
+    fn receipt(&mut self, outcome: Result<(), FailureReason>, mut effects: Effects) -> ETHReceipt {
+        if outcome.is_err() {
+            // logs from failed transactions are discarded
+            effects.logs.clear();
+        }
+        self.burned += effects.burned;
+        let mut receipt = ETHReceipt::new(outcome, effects.gas_used, effects.logs);
+        receipt.contract_address = effects.contract_address;
+        receipt
+    }
+
+    // the cached sender, if cache_senders recovered it, or else recovers it
+    fn sender_of(&mut self, txn: &ETHTxn) -> Option<ETHAddress> {
+        if !self.senders.is_empty() {
+            if let Some(sender) = self.senders.remove(&txn.hash()) {
+                return sender;
             }
-            Err(_) => return Err(FailureReason::InvalidSignature),
+        }
+        txn.get_sender_addr().ok()
+    }
+}
+
+impl ExecEnv {
+    // a signature for another chain never counts here, even if it recovers
+    // to a known sender
+    fn check_signed_for(&self, txn: &ETHTxn) -> Result<(), FailureReason> {
+        if txn.chain_id != self.chain_id {
+            return Err(FailureReason::WrongChainId);
+        }
+        if txn.encoding != self.encoding {
+            return Err(FailureReason::WrongEncoding);
+        }
+        Ok(())
+    }
+
+    // runs a transaction signed by `sender`, if its signature recovered to one
+    fn apply<S: StateAccess>(
+        &self,
+        state: &mut S,
+        sender: Option<ETHAddress>,
+        txn: &ETHTxn,
+        effects: &mut Effects,
+    ) -> Result<(), FailureReason> {
+        // make sure sender's address exists and transaction is correctly signed
+        let sender_addr = match sender {
+            Some(addr) if state.key_exists(&addr) => addr,
+            _ => return Err(FailureReason::InvalidSignature),
         };
-        if self.world_state.invalid_nonce(&txn, &sender_addr) {
+        if state.get_nonce(&sender_addr).map(|nonce| nonce + 1) != Some(txn.nonce) {
             return Err(FailureReason::InvalidNonce);
         }
+        // rejected outright, so the nonce isn't used up
+        self.prices(txn)?;
+
+        state.increment_nonce(&sender_addr);
+        self.execute(state, &sender_addr, txn, effects)
+    }
 
-        self.world_state.increment_nonce(&sender_addr);
+    // what `txn` pays per unit of gas, and for its whole gas limit
+    fn prices(&self, txn: &ETHTxn) -> Result<(Wei, Wei), FailureReason> {
+        let gasprice = txn
+            .effective_gasprice(self.base_fee)
+            .ok_or(FailureReason::FeeBelowBaseFee)?;
+        let max_fee = (gasprice * txn.gaslimit).ok_or(FailureReason::GasLimitTooHigh)?;
+        Ok((gasprice, max_fee))
+    }
 
+    // charges gas, runs the code and transfers the value, once the sender is known
+    fn execute<S: StateAccess>(
+        &self,
+        state: &mut S,
+        sender_addr: &ETHAddress,
+        txn: &ETHTxn,
+        effects: &mut Effects,
+    ) -> Result<(), FailureReason> {
         // calculate transaction fee and subtract from sender's account balance
-        let max_fee = Wei::from_gas(txn.gasprice, txn.gaslimit);
-        if !self.world_state.safe_deduct(&sender_addr, max_fee) {
+        let (gasprice, max_fee) = self.prices(txn)?;
+        if !state.safe_deduct(sender_addr, max_fee) {
             // not enough money
             return Err(FailureReason::InsufficientBalance);
         }
 
-        let mut exec_context =
-            ExecutionContext::new(txn.gaslimit, Op::from_bytes(&txn.code), txn.value);
+        // a transaction without a recipient creates a contract, running its
+        // code to get the contract's code
+        let created = match txn.recipient {
+            Some(_) => None,
+            None => Some(ETHTxn::contract_address(sender_addr, txn.nonce)),
+        };
+        let collided = created.is_some_and(|addr| occupied(state, &addr));
 
         // execute code, making sure to track new transaction value
         // terminate on invalid code or STOP instruction
-        let valid_termination = exec_context.finish_executing();
+        let result = if collided {
+            // like Ethereum, the creation fails using up all its gas
+            ExecutionResult {
+                succeeded: false,
+                gas_left: 0,
+                value: txn.value,
+                logs: vec![],
+                stack: vec![],
+                output: vec![],
+            }
+        } else {
+            self.interpreter.execute(
+                txn.gaslimit,
+                &Op::from_bytes(&txn.code),
+                txn.value,
+                &self.gas_schedule,
+            )
+        };
 
         // refund remaining gas to sender
-        let sender_refund = Wei::from_gas(txn.gasprice, exec_context.get_gas_left());
-        self.world_state.pay(&sender_addr, sender_refund);
+        let sender_refund = Wei::from_gas(gasprice, result.gas_left);
+        state.pay(sender_addr, sender_refund);
 
-        // pay miner for their work
-        let miner_fee = match max_fee - Wei::from_gas(txn.gasprice, exec_context.get_gas_left()) {
+        // burn the base fee and pay miner the rest for their work
+        let gas_used = txn.gaslimit - result.gas_left;
+        let burned = Wei::from_gas(self.base_fee, gas_used);
+        let miner_fee = match (max_fee - sender_refund).and_then(|fee| fee - burned) {
             None => panic!("gas left somehow exceeds initial gas"),
             Some(v) => v,
         };
-        self.world_state.pay(&self.miner_address, miner_fee);
+        state.pay(&self.miner_address, miner_fee);
+        effects.gas_used = gas_used;
+        effects.burned = burned;
+        effects.value = result.value;
+        effects
+            .logs
+            .extend(result.logs.into_iter().map(|entry| ETHLog {
+                address: created.unwrap_or(*sender_addr),
+                topics: entry.topics,
+                data: entry.data,
+            }));
 
         // terminate early if code was invalid
-        if !valid_termination {
+        if collided {
+            return Err(FailureReason::AddressCollision);
+        }
+        if !result.succeeded {
             return Err(FailureReason::InvalidCode);
         }
 
         // complete transaction if the value doesn't exceed the money in the sender's account
-        if !self
-            .world_state
-            .safe_deduct(&sender_addr, exec_context.get_value())
-        {
+        if !state.safe_deduct(sender_addr, result.value) {
             return Err(FailureReason::InsufficientBalance);
         }
-        self.world_state
-            .pay(&txn.recipient, exec_context.get_value());
+        if let Some(recipient) = txn.recipient {
+            state.pay(&recipient, result.value);
+        } else if let Some(addr) = created {
+            state.pay(&addr, result.value);
+            state.set_code(&addr, result.output);
+            // a nonce marks the address as taken even if the code is empty
+            state.increment_nonce(&addr);
+            effects.contract_address = Some(addr);
+        }
         Ok(())
     }
 }
 
+// whether a contract can't be created at `addr`; a balance alone doesn't
+// count, since anyone can pay an address before its contract exists
+fn occupied<S: StateAccess>(state: &S, addr: &ETHAddress) -> bool {
+    state.get_nonce(addr).is_some_and(|nonce| nonce > 0)
+        || state.get_code(addr).is_some_and(|code| !code.is_empty())
+}
+
 #[cfg(test)]
 mod tests {
+    use std::sync::Arc;
+
     use super::{
         super::aliases::ETHAddress, super::ethstate::ETHState,
-        super::ethtxn::utils::get_bs_ecsda_field, super::ethtxn::ETHTxn, super::wei::Wei, EVMState,
-        FailureReason,
+        super::ethtxn::utils::get_bs_ecsda_field, super::ethtxn::ETHTxn,
+        super::ethtxn::DEFAULT_CHAIN_ID, super::rlp::HashEncoding,
+        super::threaded::ThreadedInterpreter, super::wei::Wei, EVMState, FailureReason,
     };
 
     struct Ctx {
@@ -138,11 +490,15 @@ mod tests {
 
         let sample_txn = ETHTxn {
             nonce: 1,
+            chain_id: DEFAULT_CHAIN_ID,
             gasprice: Wei::from_wei(20),
+            max_fee: None,
+            max_priority_fee: None,
             gaslimit: 0,
-            recipient: receiver_addr.clone(),
+            recipient: Some(receiver_addr.clone()),
             value: Wei::from_wei(10),
             code: vec![],
+            encoding: HashEncoding::Bincode,
             ecdsa_fields: get_bs_ecsda_field(&secp256k1::SecretKey::random(&mut rng)),
         };
 
@@ -566,4 +922,421 @@ mod tests {
             Wei::from_wei(expected_miner_balance)
         );
     }
+
+    #[test]
+    fn receipt_logs() {
+        let mut test_ctx = get_basic_test_ctx();
+
+        test_ctx
+            .evm_state
+            .world_state
+            .pay(&test_ctx.sender, Wei::from_wei(10_000));
+
+        // [PUSH1(7), PUSH1(42), LOG1, STOP]
+        test_ctx.txn.code = vec![0x60, 7, 0x60, 42, 0xa1, 0x00];
+        test_ctx.txn.gaslimit = 1000;
+        test_ctx.txn.gasprice = Wei::from_wei(1);
+        test_ctx.sign_transaction();
+
+        let receipt = test_ctx.evm_state.execute_transaction(&test_ctx.txn);
+        assert_eq!(receipt.outcome, Ok(()));
+        assert_eq!(receipt.gas_used, 3 + 3 + 375 + 375 + 8);
+        assert_eq!(receipt.logs.len(), 1);
+        assert_eq!(receipt.logs[0].address, test_ctx.sender);
+        assert_eq!(receipt.logs[0].topics, vec![7]);
+        assert_eq!(receipt.logs[0].data, 42);
+        assert!(receipt.logs_bloom.contains_address(&test_ctx.sender));
+        assert!(receipt.logs_bloom.contains_topic(7));
+
+        // logs are dropped when the value transfer fails
+        test_ctx.txn.nonce += 1;
+        test_ctx.txn.value = Wei::from_wei(1_000_000);
+        test_ctx.sign_transaction();
+        let receipt = test_ctx.evm_state.execute_transaction(&test_ctx.txn);
+        assert_eq!(receipt.outcome, Err(FailureReason::InsufficientBalance));
+        assert!(receipt.logs.is_empty());
+        assert!(receipt.logs_bloom.is_empty());
+    }
+
+    #[test]
+    fn simulation_leaves_state_untouched() {
+        let mut test_ctx = get_basic_test_ctx();
+        test_ctx
+            .evm_state
+            .world_state
+            .pay(&test_ctx.sender, Wei::from_wei(1000));
+
+        // [PUSH1(2), ADDVAL, STOP], and the stale signature doesn't matter
+        test_ctx.txn.code = vec![0x60, 2, 0xb1, 0x00];
+        test_ctx.txn.gaslimit = 100;
+        test_ctx.txn.gasprice = Wei::from_wei(1);
+        test_ctx.txn.nonce = 40;
+
+        let simulation = test_ctx
+            .evm_state
+            .simulate_transaction(&test_ctx.txn, &test_ctx.sender);
+        assert_eq!(simulation.outcome, Ok(()));
+        assert_eq!(simulation.gas_used, 3 + 2);
+        assert_eq!(simulation.value, Wei::from_wei(12));
+        assert_eq!(
+            test_ctx.evm_state.world_state.get_value(&test_ctx.sender),
+            Some(Wei::from_wei(1000))
+        );
+        assert_eq!(
+            test_ctx.evm_state.world_state.get_nonce(&test_ctx.sender),
+            Some(0)
+        );
+        assert!(!test_ctx
+            .evm_state
+            .world_state
+            .key_exists(&test_ctx.receiver));
+
+        // an unfunded sender can't pay for gas
+        let simulation = test_ctx
+            .evm_state
+            .simulate_transaction(&test_ctx.txn, &test_ctx.receiver);
+        assert_eq!(simulation.outcome, Err(FailureReason::InsufficientBalance));
+    }
+
+    #[test]
+    fn estimate_gas_finds_minimum_limit() {
+        let mut test_ctx = get_basic_test_ctx();
+        test_ctx
+            .evm_state
+            .world_state
+            .pay(&test_ctx.sender, Wei::from_wei(100_000));
+        test_ctx.txn.gasprice = Wei::from_wei(1);
+
+        // [PUSH1(2), PUSH1(3), MUL, PUSH1(7), LOG0, STOP]
+        test_ctx.txn.code = vec![0x60, 2, 0x60, 3, 0x02, 0x60, 7, 0xa0, 0x00];
+        let estimate = test_ctx
+            .evm_state
+            .estimate_gas(&test_ctx.txn, &test_ctx.sender)
+            .unwrap();
+        assert_eq!(estimate, 3 + 3 + 5 + 3 + 375 + 8);
+
+        test_ctx.txn.gaslimit = estimate;
+        test_ctx.sign_transaction();
+        assert_eq!(test_ctx.evm_state.run_transaction(&test_ctx.txn), Ok(()));
+
+        // code that never stops has no estimate
+        test_ctx.txn.code = vec![0x60, 0, 0x56];
+        assert_eq!(
+            test_ctx
+                .evm_state
+                .estimate_gas(&test_ctx.txn, &test_ctx.sender),
+            Err(FailureReason::InvalidCode)
+        );
+    }
+
+    #[test]
+    fn oversized_gaslimit_rejected() {
+        let mut test_ctx = get_basic_test_ctx();
+        test_ctx
+            .evm_state
+            .world_state
+            .pay(&test_ctx.sender, Wei::from_wei(1000));
+        test_ctx.txn.gasprice = Wei::from_wei(2);
+        test_ctx.txn.gaslimit = u128::MAX / 2 + 1;
+        test_ctx.sign_transaction();
+
+        assert_eq!(
+            test_ctx.evm_state.run_transaction(&test_ctx.txn),
+            Err(FailureReason::GasLimitTooHigh)
+        );
+        let state = test_ctx.evm_state.get_world_state();
+        assert_eq!(state.get_nonce(&test_ctx.sender), Some(0));
+        assert_eq!(state.get_value(&test_ctx.sender), Some(Wei::from_wei(1000)));
+        assert_eq!(
+            test_ctx
+                .evm_state
+                .simulate_transaction(&test_ctx.txn, &test_ctx.sender)
+                .outcome,
+            Err(FailureReason::GasLimitTooHigh)
+        );
+    }
+
+    #[test]
+    fn threaded_interpreter_receipts() {
+        let mut test_ctx = get_basic_test_ctx();
+        test_ctx
+            .evm_state
+            .set_interpreter(Arc::new(ThreadedInterpreter));
+        test_ctx
+            .evm_state
+            .world_state
+            .pay(&test_ctx.sender, Wei::from_wei(10_000));
+
+        // [PUSH1(7), PUSH1(42), LOG1, PUSH1(1), ADDVAL, STOP]
+        test_ctx.txn.code = vec![0x60, 7, 0x60, 42, 0xa1, 0x60, 1, 0xb1, 0x00];
+        test_ctx.txn.gaslimit = 1000;
+        test_ctx.txn.gasprice = Wei::from_wei(1);
+        test_ctx.sign_transaction();
+
+        let receipt = test_ctx.evm_state.execute_transaction(&test_ctx.txn);
+        assert_eq!(receipt.outcome, Ok(()));
+        assert_eq!(receipt.gas_used, 3 + 3 + 375 + 375 + 8 + 3 + 2);
+        assert_eq!(receipt.logs[0].topics, vec![7]);
+        assert_eq!(
+            test_ctx.evm_state.world_state.get_value(&test_ctx.receiver),
+            Some(Wei::from_wei(11))
+        );
+    }
+
+    #[test]
+    fn base_fee_is_burned() {
+        let mut test_ctx = get_basic_test_ctx();
+        test_ctx
+            .evm_state
+            .world_state
+            .pay(&test_ctx.sender, Wei::from_wei(10_000));
+        test_ctx.evm_state.set_base_fee(Wei::from_wei(5));
+
+        // [PUSH1(1), ADDVAL, STOP] uses 5 gas; pays 5 + 2 per unit
+        test_ctx.txn.code = vec![0x60, 1, 0xb1, 0x00];
+        test_ctx.txn.gaslimit = 100;
+        test_ctx.txn.value = Wei::from_wei(0);
+        test_ctx.txn.max_fee = Some(Wei::from_wei(20));
+        test_ctx.txn.max_priority_fee = Some(Wei::from_wei(2));
+        test_ctx.sign_transaction();
+        assert_eq!(test_ctx.evm_state.run_transaction(&test_ctx.txn), Ok(()));
+
+        let state = test_ctx.evm_state.get_world_state();
+        assert_eq!(state.get_value(&test_ctx.miner), Some(Wei::from_wei(5 * 2)));
+        assert_eq!(
+            state.get_value(&test_ctx.sender),
+            Some(Wei::from_wei(10_000 - 5 * 7 - 1))
+        );
+        assert_eq!(test_ctx.evm_state.get_burned(), Wei::from_wei(5 * 5));
+
+        // a legacy gasprice is both caps, so the miner gets what's left of it
+        test_ctx.txn.nonce += 1;
+        test_ctx.txn.max_fee = None;
+        test_ctx.txn.max_priority_fee = None;
+        test_ctx.txn.gasprice = Wei::from_wei(6);
+        test_ctx.sign_transaction();
+        assert_eq!(test_ctx.evm_state.run_transaction(&test_ctx.txn), Ok(()));
+        assert_eq!(
+            test_ctx
+                .evm_state
+                .get_world_state()
+                .get_value(&test_ctx.miner),
+            Some(Wei::from_wei(5 * 2 + 5))
+        );
+        assert_eq!(test_ctx.evm_state.get_burned(), Wei::from_wei(5 * 5 * 2));
+
+        // a max fee under the base fee is turned away with the nonce unused
+        test_ctx.txn.nonce += 1;
+        test_ctx.txn.gasprice = Wei::from_wei(4);
+        test_ctx.sign_transaction();
+        assert_eq!(
+            test_ctx.evm_state.run_transaction(&test_ctx.txn),
+            Err(FailureReason::FeeBelowBaseFee)
+        );
+        assert_eq!(
+            test_ctx
+                .evm_state
+                .get_world_state()
+                .get_nonce(&test_ctx.sender),
+            Some(2)
+        );
+    }
+
+    #[test]
+    fn other_chains_signatures_rejected() {
+        let mut test_ctx = get_basic_test_ctx();
+        test_ctx
+            .evm_state
+            .world_state
+            .pay(&test_ctx.sender, Wei::from_wei(100));
+        test_ctx.evm_state.set_chain_id(DEFAULT_CHAIN_ID + 1);
+        assert_eq!(
+            test_ctx.evm_state.run_transaction(&test_ctx.txn),
+            Err(FailureReason::WrongChainId)
+        );
+
+        // relabelling the transaction changes what was signed
+        test_ctx.txn.chain_id = DEFAULT_CHAIN_ID + 1;
+        assert_eq!(
+            test_ctx.evm_state.run_transaction(&test_ctx.txn),
+            Err(FailureReason::InvalidSignature)
+        );
+        assert_eq!(
+            test_ctx
+                .evm_state
+                .get_world_state()
+                .get_nonce(&test_ctx.sender),
+            Some(0)
+        );
+
+        test_ctx.sign_transaction();
+        assert_eq!(test_ctx.evm_state.run_transaction(&test_ctx.txn), Ok(()));
+    }
+
+    #[test]
+    fn other_encodings_rejected() {
+        let mut test_ctx = get_basic_test_ctx();
+        test_ctx
+            .evm_state
+            .world_state
+            .pay(&test_ctx.sender, Wei::from_wei(100));
+        test_ctx.evm_state.set_encoding(HashEncoding::Rlp);
+        assert_eq!(
+            test_ctx.evm_state.run_transaction(&test_ctx.txn),
+            Err(FailureReason::WrongEncoding)
+        );
+
+        test_ctx.txn.encoding = HashEncoding::Rlp;
+        test_ctx.sign_transaction();
+        assert_eq!(test_ctx.evm_state.run_transaction(&test_ctx.txn), Ok(()));
+    }
+
+    #[test]
+    fn transactions_without_recipients_create_contracts() {
+        let mut test_ctx = get_basic_test_ctx();
+        test_ctx
+            .evm_state
+            .world_state
+            .pay(&test_ctx.sender, Wei::from_wei(10_000));
+
+        // stores [7, 8] and returns it as the contract's code
+        test_ctx.txn.recipient = None;
+        test_ctx.txn.code = vec![
+            0x60, 7, 0x60, 10, 0x52, 0x60, 8, 0x60, 11, 0x52, 0x60, 2, 0x60, 10, 0xf3,
+        ];
+        test_ctx.txn.gaslimit = 1000;
+        test_ctx.txn.gasprice = Wei::from_wei(1);
+        test_ctx.sign_transaction();
+
+        let receipt = test_ctx.evm_state.execute_transaction(&test_ctx.txn);
+        assert_eq!(receipt.outcome, Ok(()));
+        let contract = ETHTxn::contract_address(&test_ctx.sender, test_ctx.txn.nonce);
+        assert_eq!(receipt.contract_address, Some(contract));
+        let state = test_ctx.evm_state.get_world_state();
+        assert_eq!(state.get_code(&contract), Some(&[7, 8][..]));
+        assert_eq!(state.get_value(&contract), Some(Wei::from_wei(10)));
+        assert_eq!(state.get_nonce(&contract), Some(1));
+
+        // payments leave no contract address behind
+        test_ctx.txn.nonce += 1;
+        test_ctx.txn.recipient = Some(test_ctx.receiver);
+        test_ctx.sign_transaction();
+        let receipt = test_ctx.evm_state.execute_transaction(&test_ctx.txn);
+        assert_eq!(receipt.outcome, Ok(()));
+        assert_eq!(receipt.contract_address, None);
+    }
+
+    // a funded creation transaction whose contract address already has a
+    // balance of 5
+    fn creation_at_a_funded_address() -> (Ctx, ETHAddress) {
+        let mut test_ctx = get_basic_test_ctx();
+        test_ctx
+            .evm_state
+            .world_state
+            .pay(&test_ctx.sender, Wei::from_wei(10_000));
+        let contract = ETHTxn::contract_address(&test_ctx.sender, test_ctx.txn.nonce);
+        test_ctx
+            .evm_state
+            .world_state
+            .pay(&contract, Wei::from_wei(5));
+
+        test_ctx.txn.recipient = None;
+        test_ctx.txn.code = vec![0x00];
+        test_ctx.txn.gaslimit = 1000;
+        test_ctx.txn.gasprice = Wei::from_wei(1);
+        test_ctx.sign_transaction();
+        (test_ctx, contract)
+    }
+
+    #[test]
+    fn creation_keeps_an_existing_balance() {
+        let (mut test_ctx, contract) = creation_at_a_funded_address();
+        let receipt = test_ctx.evm_state.execute_transaction(&test_ctx.txn);
+        assert_eq!(receipt.outcome, Ok(()));
+        assert_eq!(receipt.contract_address, Some(contract));
+        let state = test_ctx.evm_state.get_world_state();
+        assert_eq!(
+            state.get_value(&contract),
+            Some(Wei::from_wei(5) + test_ctx.txn.value)
+        );
+        assert_eq!(state.get_nonce(&contract), Some(1));
+    }
+
+    #[test]
+    fn creation_address_collisions_fail() {
+        let (mut test_ctx, contract) = creation_at_a_funded_address();
+        test_ctx.evm_state.world_state.set_code(&contract, vec![0]);
+
+        let receipt = test_ctx.evm_state.execute_transaction(&test_ctx.txn);
+        assert_eq!(receipt.outcome, Err(FailureReason::AddressCollision));
+        assert_eq!(receipt.gas_used, 1000);
+        assert_eq!(receipt.contract_address, None);
+        let state = test_ctx.evm_state.get_world_state();
+        assert_eq!(state.get_value(&contract), Some(Wei::from_wei(5)));
+        assert_eq!(state.get_code(&contract), Some(&[0][..]));
+        assert_eq!(state.get_nonce(&test_ctx.sender), Some(1));
+        assert_eq!(
+            state.get_value(&test_ctx.sender),
+            Some(Wei::from_wei(10_000 - 1000))
+        );
+    }
+
+    #[test]
+    fn block_prepass_matches_serial_recovery() {
+        let keys: Vec<_> = (1..=4)
+            .map(|seed| secp256k1::SecretKey::parse(&[seed; 32]).unwrap())
+            .collect();
+        let funded = || {
+            let mut state = ETHState::default();
+            for key in &keys {
+                let pubkey = secp256k1::PublicKey::from_secret_key(key);
+                let addr = ETHTxn::get_address_from_public_key(&pubkey).unwrap();
+                state.pay(&addr, Wei::from_wei(1000));
+            }
+            EVMState::new(state, [0; 20])
+        };
+        let mut txns = vec![];
+        for i in 0..12 {
+            let key = &keys[i % keys.len()];
+            let mut txn = ETHTxn {
+                nonce: (i / keys.len()) as u32 + 1,
+                chain_id: DEFAULT_CHAIN_ID,
+                gasprice: Wei::from_wei(1),
+                max_fee: None,
+                max_priority_fee: None,
+                gaslimit: 10,
+                recipient: Some([i as u8; 20]),
+                value: Wei::from_wei(i as u128),
+                code: vec![],
+                encoding: HashEncoding::Bincode,
+                ecdsa_fields: get_bs_ecsda_field(key),
+            };
+            txn.sign_transaction(key);
+            txns.push(txn);
+        }
+        // a forged signature, a repeat, and one for another chain
+        txns[5].value = Wei::from_wei(500);
+        let repeat = txns[0].clone();
+        txns.push(repeat);
+        txns[2].chain_id += 1;
+
+        let mut serial = funded();
+        let expected: Vec<_> = txns
+            .iter()
+            .map(|txn| serial.execute_transaction(txn))
+            .collect();
+        assert_eq!(expected[5].outcome, Err(FailureReason::InvalidSignature));
+        for num_threads in 1..=4 {
+            let mut batched = funded();
+            assert_eq!(batched.execute_block(&txns, num_threads), expected);
+            assert!(batched.senders.is_empty());
+            for i in 0..12 {
+                let addr = [i as u8; 20];
+                assert_eq!(
+                    batched.get_world_state().get_value(&addr),
+                    serial.get_world_state().get_value(&addr)
+                );
+            }
+        }
+    }
 }
@@ -0,0 +1,109 @@
+use super::gas::Gas;
+use super::wei::Wei;
+
+// the base fee moves by at most 1/8 per block
+const BASE_FEE_CHANGE_DENOMINATOR: u128 = 8;
+
+/// A base fee per block that rises when the parent block used more than
+/// `gas_target` gas and falls when it used less. The base fee is burned;
+/// miners only get what transactions pay on top of it.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct FeeMarket {
+    pub initial_base_fee: Wei,
+    pub gas_target: Gas,
+}
+
+impl FeeMarket {
+    pub fn new(initial_base_fee: Wei, gas_target: Gas) -> FeeMarket {
+        FeeMarket {
+            initial_base_fee,
+            gas_target,
+        }
+    }
+
+    /// The base fee for a block whose parent had `parent_base_fee` and used
+    /// `parent_gas_used`. A parent without a base fee predates the market,
+    /// so its child starts at `initial_base_fee`.
+    pub fn next_base_fee(&self, parent_base_fee: Wei, parent_gas_used: Gas) -> Wei {
+        let base_fee = parent_base_fee.get_wei();
+        if base_fee == 0 {
+            return self.initial_base_fee;
+        }
+        if self.gas_target == 0 || parent_gas_used == self.gas_target {
+            return parent_base_fee;
+        }
+        let (gas_delta, rising) = if parent_gas_used > self.gas_target {
+            (parent_gas_used - self.gas_target, true)
+        } else {
+            (self.gas_target - parent_gas_used, false)
+        };
+        let change =
+            base_fee.saturating_mul(gas_delta) / self.gas_target / BASE_FEE_CHANGE_DENOMINATOR;
+        if rising {
+            // always rises by something, or a small base fee would be stuck
+            parent_base_fee.saturating_add(Wei::from_wei(change.max(1)))
+        } else {
+            parent_base_fee.saturating_sub(Wei::from_wei(change))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{super::gas::Gas, super::wei::Wei, FeeMarket};
+
+    #[test]
+    fn base_fee_follows_fullness() {
+        let market = FeeMarket::new(Wei::from_wei(1000), 100);
+        assert_eq!(
+            market.next_base_fee(Wei::from_wei(0), 500),
+            Wei::from_wei(1000)
+        );
+        assert_eq!(
+            market.next_base_fee(Wei::from_wei(800), 100),
+            Wei::from_wei(800)
+        );
+        // twice the target raises it by an eighth, an empty block lowers it
+        // by an eighth
+        assert_eq!(
+            market.next_base_fee(Wei::from_wei(800), 200),
+            Wei::from_wei(900)
+        );
+        assert_eq!(
+            market.next_base_fee(Wei::from_wei(800), 0),
+            Wei::from_wei(700)
+        );
+        assert_eq!(
+            market.next_base_fee(Wei::from_wei(800), 150),
+            Wei::from_wei(850)
+        );
+        assert_eq!(
+            market.next_base_fee(Wei::from_wei(1), 101),
+            Wei::from_wei(2)
+        );
+        assert_eq!(market.next_base_fee(Wei::from_wei(7), 0), Wei::from_wei(7));
+    }
+
+    #[test]
+    fn base_fee_converges_under_sustained_load() {
+        // every block, 40 senders each want 10 gas, and the i-th will pay
+        // at most 100 * i per unit; only 200 gas is targeted
+        const GAS_PER_TXN: Gas = 10;
+        let market = FeeMarket::new(Wei::from_wei(100), 200);
+        let max_fees: Vec<Wei> = (1..=40).map(|i| Wei::from_wei(100 * i)).collect();
+        let mut base_fee = market.initial_base_fee;
+        let mut history = vec![];
+        for _ in 0..300 {
+            let included = max_fees.iter().filter(|&&fee| fee >= base_fee).count() as Gas;
+            base_fee = market.next_base_fee(base_fee, included * GAS_PER_TXN);
+            history.push(base_fee.get_wei());
+        }
+        // the 20 senders paying over 2000 fill the target exactly, so the
+        // base fee settles between the 20th and 21st highest max fee
+        for &fee in &history[250..] {
+            assert!(fee > 2000 && fee <= 2300, "base fee {}", fee);
+        }
+        let spread = history[250..].iter().max().unwrap() - history[250..].iter().min().unwrap();
+        assert!(spread <= 300, "spread {}", spread);
+    }
+}
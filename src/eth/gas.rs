@@ -7,3 +7,6 @@ pub const GLOW: Gas = 5;
 pub const GMID: Gas = 8;
 pub const GHIGH: Gas = 10;
 pub const GBALANCE: Gas = 400;
+pub const GLOG: Gas = 375;
+pub const GLOGDATA: Gas = 8;
+pub const GLOGTOPIC: Gas = 375;
@@ -0,0 +1,231 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::{Arc, OnceLock};
+
+use super::gas::Gas;
+use super::ops::Op;
+
+/// Per-opcode gas costs. The default schedule is `Op::to_cost`.
+#[derive(Clone, PartialEq, Eq)]
+pub struct GasSchedule {
+    // indexed by opcode byte
+    costs: [Gas; 256],
+}
+
+impl GasSchedule {
+    // shared by every context that isn't given a schedule, so creating one
+    // doesn't have to build a table
+    pub fn shared_default() -> Arc<GasSchedule> {
+        static DEFAULT: OnceLock<Arc<GasSchedule>> = OnceLock::new();
+        DEFAULT
+            .get_or_init(|| Arc::new(GasSchedule::default()))
+            .clone()
+    }
+
+    pub fn cost(&self, op: Op) -> Gas {
+        self.costs[usize::from(op.to_byte())]
+    }
+
+    // PUSH1 and INVALID cost the same whatever their operand
+    pub fn set_cost(&mut self, op: Op, cost: Gas) {
+        self.costs[usize::from(op.to_byte())] = cost;
+    }
+
+    pub fn with_cost(mut self, op: Op, cost: Gas) -> GasSchedule {
+        self.set_cost(op, cost);
+        self
+    }
+
+    /// Applies overrides written one per line as `MNEMONIC COST`, with `#`
+    /// comments. Anything not mentioned keeps its current cost.
+    pub fn apply_overrides(&mut self, source: &str) -> Result<(), String> {
+        for (number, line) in source.lines().enumerate() {
+            if let Some((op, cost)) = parse_override(line, number + 1)? {
+                self.set_cost(op, cost);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Default for GasSchedule {
+    fn default() -> GasSchedule {
+        let mut costs = [0; 256];
+        for (byte, cost) in costs.iter_mut().enumerate() {
+            *cost = Op::from_byte(byte as u8).to_cost();
+        }
+        GasSchedule { costs }
+    }
+}
+
+impl fmt::Debug for GasSchedule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // only list the opcodes that exist
+        let mut map = f.debug_map();
+        for byte in 0..=255u8 {
+            let op = Op::from_byte(byte);
+            if let Op::INVALID(_) = op {
+                continue;
+            }
+            map.entry(&op.mnemonic(), &self.costs[usize::from(byte)]);
+        }
+        map.finish()
+    }
+}
+
+fn parse_override(line: &str, number: usize) -> Result<Option<(Op, Gas)>, String> {
+    let line = match line.find('#') {
+        Some(pos) => &line[..pos],
+        None => line,
+    };
+    let mut tokens = line.split_whitespace();
+    let (mnemonic, cost) = match (tokens.next(), tokens.next(), tokens.next()) {
+        (None, _, _) => return Ok(None),
+        (Some(mnemonic), Some(cost), None) => (mnemonic, cost),
+        _ => return Err(format!("line {}: expected MNEMONIC COST", number)),
+    };
+    let op = Op::from_mnemonic(&mnemonic.to_uppercase())
+        .ok_or_else(|| format!("line {}: unknown mnemonic '{}'", number, mnemonic))?;
+    let cost = cost
+        .parse()
+        .map_err(|_| format!("line {}: '{}' isn't a gas cost", number, cost))?;
+    Ok(Some((op, cost)))
+}
+
+/// A gas schedule that changes at given block numbers.
+#[derive(Debug, Clone)]
+pub struct ForkSchedule {
+    genesis: GasSchedule,
+    forks: BTreeMap<usize, Vec<(Op, Gas)>>,
+    // the resolved schedule for each fork, sorted by block; the first
+    // entry is always block 0
+    schedules: Vec<(usize, Arc<GasSchedule>)>,
+}
+
+impl ForkSchedule {
+    pub fn new(genesis: GasSchedule) -> ForkSchedule {
+        ForkSchedule {
+            schedules: vec![(0, Arc::new(genesis.clone()))],
+            genesis,
+            forks: BTreeMap::new(),
+        }
+    }
+
+    /// From block `block` onwards, `op` costs `cost`. Later forks inherit
+    /// the change unless they override it too.
+    pub fn add_fork(&mut self, block: usize, op: Op, cost: Gas) {
+        self.forks.entry(block).or_default().push((op, cost));
+        // rebuilt from scratch so the order forks are added in doesn't matter
+        let mut current = self.genesis.clone();
+        self.schedules.clear();
+        let mut start = 0;
+        for (&block, changes) in &self.forks {
+            if block > start {
+                self.schedules.push((start, Arc::new(current.clone())));
+                start = block;
+            }
+            for &(op, cost) in changes {
+                current.set_cost(op, cost);
+            }
+        }
+        self.schedules.push((start, Arc::new(current)));
+    }
+
+    /// Parses a genesis schedule and forks from override lines. A line
+    /// `from block N` starts the overrides for the fork at block N.
+    ///
+    /// ```text
+    /// JUMP 10
+    /// from block 100
+    /// JUMP 12     # JUMP costs 12 from block 100 onwards
+    /// ```
+    pub fn parse(source: &str) -> Result<ForkSchedule, String> {
+        let mut forks = ForkSchedule::new(GasSchedule::default());
+        let mut block = 0;
+        for (number, line) in source.lines().enumerate() {
+            let words: Vec<&str> = line.split('#').next().unwrap().split_whitespace().collect();
+            if words.len() == 3 && words[0] == "from" && words[1] == "block" {
+                block = words[2].parse().map_err(|_| {
+                    format!("line {}: '{}' isn't a block number", number + 1, words[2])
+                })?;
+                continue;
+            }
+            if let Some((op, cost)) = parse_override(line, number + 1)? {
+                forks.add_fork(block, op, cost);
+            }
+        }
+        Ok(forks)
+    }
+
+    pub fn at_block(&self, block: usize) -> Arc<GasSchedule> {
+        let pos = match self.schedules.binary_search_by_key(&block, |&(b, _)| b) {
+            Ok(pos) => pos,
+            Err(pos) => pos - 1,
+        };
+        self.schedules[pos].1.clone()
+    }
+}
+
+impl Default for ForkSchedule {
+    fn default() -> ForkSchedule {
+        ForkSchedule::new(GasSchedule::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{super::ops::Op, ForkSchedule, GasSchedule};
+
+    #[test]
+    fn default_matches_to_cost() {
+        let schedule = GasSchedule::default();
+        for byte in 0..=255u8 {
+            let op = Op::from_byte(byte);
+            assert_eq!(schedule.cost(op), op.to_cost());
+        }
+        assert_eq!(schedule.cost(Op::PUSH1(9)), Op::PUSH1(0).to_cost());
+    }
+
+    #[test]
+    fn overrides() {
+        let mut schedule = GasSchedule::default().with_cost(Op::ADD, 7);
+        assert_eq!(schedule.cost(Op::ADD), 7);
+        schedule
+            .apply_overrides("# cheaper jumps\njump 1\nPUSH1 0  # free pushes\n\n")
+            .unwrap();
+        assert_eq!(schedule.cost(Op::JUMP), 1);
+        assert_eq!(schedule.cost(Op::PUSH1(4)), 0);
+        assert_eq!(schedule.cost(Op::ADD), 7);
+
+        assert!(schedule.apply_overrides("FROB 3").is_err());
+        assert!(schedule.apply_overrides("ADD").is_err());
+        assert!(schedule.apply_overrides("ADD -1").is_err());
+    }
+
+    #[test]
+    fn forks_apply_from_their_block() {
+        let mut forks = ForkSchedule::new(GasSchedule::default());
+        forks.add_fork(100, Op::JUMP, 12);
+        forks.add_fork(50, Op::ADD, 1);
+        forks.add_fork(200, Op::JUMP, 20);
+        forks.add_fork(150, Op::JUMP, 15);
+
+        assert_eq!(forks.at_block(0).cost(Op::JUMP), Op::JUMP.to_cost());
+        assert_eq!(forks.at_block(49).cost(Op::ADD), Op::ADD.to_cost());
+        assert_eq!(forks.at_block(50).cost(Op::ADD), 1);
+        assert_eq!(forks.at_block(99).cost(Op::JUMP), Op::JUMP.to_cost());
+        assert_eq!(forks.at_block(100).cost(Op::JUMP), 12);
+        // later forks keep earlier changes
+        assert_eq!(forks.at_block(150).cost(Op::ADD), 1);
+        assert_eq!(forks.at_block(199).cost(Op::JUMP), 15);
+        assert_eq!(forks.at_block(1000).cost(Op::JUMP), 20);
+
+        let parsed =
+            ForkSchedule::parse("ADD 1\nfrom block 100\nJUMP 12\nfrom block 200\nJUMP 20").unwrap();
+        assert_eq!(parsed.at_block(0).cost(Op::ADD), 1);
+        assert_eq!(parsed.at_block(100).cost(Op::JUMP), 12);
+        assert_eq!(parsed.at_block(250).cost(Op::JUMP), 20);
+        assert_eq!(parsed.at_block(250).cost(Op::ADD), 1);
+        assert!(ForkSchedule::parse("from block soon").is_err());
+    }
+}
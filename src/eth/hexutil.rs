@@ -0,0 +1,38 @@
+/// Decodes a hex string such as "6002b100" or "0x6002 b100" into bytes.
+/// Whitespace is ignored, as is an optional leading "0x".
+pub fn decode(hex: &str) -> Result<Vec<u8>, String> {
+    let hex = hex.trim();
+    let hex = hex
+        .strip_prefix("0x")
+        .or_else(|| hex.strip_prefix("0X"))
+        .unwrap_or(hex);
+    let digits: Vec<char> = hex.chars().filter(|c| !c.is_whitespace()).collect();
+    if let Some(c) = digits.iter().find(|c| !c.is_ascii_hexdigit()) {
+        return Err(format!("invalid hex digit '{}'", c));
+    }
+    if !digits.len().is_multiple_of(2) {
+        return Err(format!("odd number of hex digits ({})", digits.len()));
+    }
+    Ok(digits
+        .chunks(2)
+        .map(|pair| ((pair[0].to_digit(16).unwrap() << 4) | pair[1].to_digit(16).unwrap()) as u8)
+        .collect())
+}
+
+pub fn encode(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode, encode};
+
+    #[test]
+    fn test_hex_round_trip() {
+        assert_eq!(decode("0x6002 b100"), Ok(vec![0x60, 0x02, 0xb1, 0x00]));
+        assert_eq!(encode(&[0x60, 0x02, 0xb1, 0x00]), "6002b100");
+        assert_eq!(decode(""), Ok(vec![]));
+        assert!(decode("600").is_err());
+        assert!(decode("zz").is_err());
+    }
+}
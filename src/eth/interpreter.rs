@@ -0,0 +1,260 @@
+use std::sync::Arc;
+
+use super::evmexec::{ExecutionContext, LogEntry};
+use super::gas::Gas;
+use super::gasschedule::GasSchedule;
+use super::ops::Op;
+use super::wei::Wei;
+
+/// How a run of some code ended.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ExecutionResult {
+    // false if the code terminated abnormally
+    pub succeeded: bool,
+    pub gas_left: Gas,
+    pub value: Wei,
+    pub logs: Vec<LogEntry>,
+    pub stack: Vec<u8>,
+    // what RETURN handed back, empty if the code stopped any other way
+    pub output: Vec<u8>,
+}
+
+/// An execution backend. Every backend must give the same result for the
+/// same code, gas, value and schedule.
+pub trait Interpreter {
+    fn execute(
+        &self,
+        gaslimit: Gas,
+        code: &[Op],
+        txn_value: Wei,
+        schedule: &Arc<GasSchedule>,
+    ) -> ExecutionResult;
+}
+
+/// The reference backend: `ExecutionContext`'s match on every op.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct MatchInterpreter;
+
+impl Interpreter for MatchInterpreter {
+    fn execute(
+        &self,
+        gaslimit: Gas,
+        code: &[Op],
+        txn_value: Wei,
+        schedule: &Arc<GasSchedule>,
+    ) -> ExecutionResult {
+        let mut context = ExecutionContext::new(gaslimit, code.to_vec(), txn_value);
+        context.set_gas_schedule(schedule.clone());
+        let succeeded = context.finish_executing();
+        ExecutionResult {
+            succeeded,
+            gas_left: context.get_gas_left(),
+            value: context.get_value(),
+            logs: context.get_logs().to_vec(),
+            stack: context.get_stack().to_vec(),
+            output: context.get_output().to_vec(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use rand::Rng;
+
+    use super::{
+        super::asm::assemble, super::gas::Gas, super::gasschedule::GasSchedule, super::ops::Op,
+        super::ops::Op::*, super::threaded::ThreadedInterpreter, super::wei::Wei, Interpreter,
+        MatchInterpreter,
+    };
+
+    fn backends() -> Vec<(&'static str, Box<dyn Interpreter>)> {
+        vec![
+            ("match", Box::new(MatchInterpreter)),
+            ("threaded", Box::new(ThreadedInterpreter)),
+        ]
+    }
+
+    // (assembly, gas limit, initial value, succeeds, expected value)
+    const CONFORMANCE: &[(&str, Gas, u128, bool, u128)] = &[
+        ("", 0, 3, true, 3),
+        ("STOP", 0, 3, true, 3),
+        ("PUSH1 2\nADDVAL\nSTOP", 100, 76, true, 78),
+        // the op takes effect before its gas is found to be missing
+        ("PUSH1 2\nADDVAL\nSTOP", 4, 76, false, 78),
+        ("PUSH1 9\nSUBVAL\nSTOP", 100, 4, true, 0),
+        ("PUSH1 9\nADDVAL\nSTOP", 100, u128::MAX - 4, true, u128::MAX),
+        ("PUSH1 6\nSETVAL", 100, 0, false, 6),
+        ("PUSH1 3\nPUSH1 5\nSUB\nSETVAL\nSTOP", 100, 9, true, 2),
+        ("PUSH1 5\nPUSH1 3\nSUB\nSETVAL\nSTOP", 100, 9, true, 0),
+        ("PUSH1 0\nPUSH1 7\nDIV\nSETVAL\nSTOP", 100, 9, true, 0),
+        (
+            "PUSH1 4\nPUSH1 3\nLT\nPUSH1 4\nPUSH1 3\nGT\nADD\nSETVAL\nSTOP",
+            100,
+            0,
+            true,
+            1,
+        ),
+        (
+            "PUSH1 0\nISZERO\nPUSH1 1\nEQ\nSETVAL\nSTOP",
+            100,
+            0,
+            true,
+            1,
+        ),
+        (
+            "PUSH1 8\nPUSH1 40\nMSTORE\nPUSH1 40\nMLOAD\nSETVAL\nSTOP",
+            100,
+            0,
+            true,
+            8,
+        ),
+        ("JUMP skip\nINVALID(0x0c)\nskip: STOP", 100, 0, true, 0),
+        (
+            "PUSH1 1\nJUMPI skip\nINVALID(0x0c)\nskip: STOP",
+            100,
+            0,
+            true,
+            0,
+        ),
+        (
+            "PUSH1 0\nJUMPI skip\nINVALID(0x0c)\nskip: STOP",
+            100,
+            0,
+            false,
+            0,
+        ),
+        ("PUSH1 200\nJUMP", 100, 0, false, 0),
+        // a jump straight onto a JUMP that normally follows a push
+        (
+            "PUSH1 6\nPUSH1 4\nJUMP\nPUSH1 9\nJUMP\nSTOP\nPUSH1 1\nSETVAL\nSTOP",
+            100,
+            0,
+            true,
+            1,
+        ),
+        ("ADD", 100, 0, false, 0),
+        (
+            "PUSH1 1\nLOG0\nPUSH1 2\nPUSH1 3\nLOG1\nSTOP",
+            2000,
+            0,
+            true,
+            0,
+        ),
+        ("PUSH1 1\nPUSH1 255\nRETURN", 100, 0, true, 0),
+        // reaching past the end of memory
+        ("PUSH1 2\nPUSH1 255\nRETURN", 100, 0, false, 0),
+        ("PUSH1 0\nRETURN", 100, 0, false, 0),
+        (
+            "PUSH1 3
+             PUSH1 0
+             MSTORE
+             loop:
+             PUSH1 0
+             MLOAD
+             ISZERO
+             JUMPI end
+             PUSH1 1
+             ADDVAL
+             PUSH1 1
+             PUSH1 0
+             MLOAD
+             SUB
+             PUSH1 0
+             MSTORE
+             JUMP loop
+             end: STOP",
+            1000,
+            0,
+            true,
+            3,
+        ),
+    ];
+
+    #[test]
+    fn conformance() {
+        for (source, gaslimit, value, succeeds, expected) in CONFORMANCE {
+            let code = Op::from_bytes(&assemble(source).unwrap());
+            let schedule = GasSchedule::shared_default();
+            let reference =
+                MatchInterpreter.execute(*gaslimit, &code, Wei::from_wei(*value), &schedule);
+            for (name, backend) in backends() {
+                let result = backend.execute(*gaslimit, &code, Wei::from_wei(*value), &schedule);
+                assert_eq!(result.succeeded, *succeeds, "{} on {}", name, source);
+                assert_eq!(
+                    result.value,
+                    Wei::from_wei(*expected),
+                    "{} on {}",
+                    name,
+                    source
+                );
+                assert_eq!(result, reference, "{} on {}", name, source);
+            }
+        }
+    }
+
+    #[test]
+    fn conformance_custom_schedule() {
+        let schedule = Arc::new(
+            GasSchedule::default()
+                .with_cost(JUMP, 30)
+                .with_cost(PUSH1(0), 1),
+        );
+        let code = Op::from_bytes(&assemble("JUMP end\nend: PUSH1 1\nADDVAL\nSTOP").unwrap());
+        for (name, backend) in backends() {
+            let result = backend.execute(100, &code, Wei::from_wei(0), &schedule);
+            assert!(result.succeeded, "{}", name);
+            assert_eq!(result.gas_left, 100 - 1 - 30 - 1 - 2, "{}", name);
+            let result = backend.execute(30, &code, Wei::from_wei(0), &schedule);
+            assert!(!result.succeeded, "{}", name);
+            assert_eq!(result.gas_left, 29, "{}", name);
+        }
+    }
+
+    #[test]
+    fn random_programs_agree() {
+        let pool = [
+            STOP,
+            ADD,
+            MUL,
+            SUB,
+            DIV,
+            LT,
+            GT,
+            EQ,
+            ISZERO,
+            POP,
+            MLOAD,
+            MSTORE,
+            JUMP,
+            JUMPI,
+            SETVAL,
+            ADDVAL,
+            SUBVAL,
+            LOG0,
+            LOG2,
+            INVALID(0x0c),
+        ];
+        let schedule = GasSchedule::shared_default();
+        let mut rng = rand::thread_rng();
+        for _ in 0..2000 {
+            let len = rng.gen_range(1, 24);
+            let code: Vec<Op> = (0..len)
+                .map(|_| {
+                    if rng.gen_range(0, 2) == 0 {
+                        PUSH1(rng.gen_range(0, len as u8 + 2))
+                    } else {
+                        pool[rng.gen_range(0, pool.len())]
+                    }
+                })
+                .collect();
+            // loops are cut short by the gas limit
+            let gaslimit = Gas::from(rng.gen_range(0u32, 1200));
+            let reference = MatchInterpreter.execute(gaslimit, &code, Wei::from_wei(5), &schedule);
+            let threaded =
+                ThreadedInterpreter.execute(gaslimit, &code, Wei::from_wei(5), &schedule);
+            assert_eq!(threaded, reference, "code {:?} gas {}", code, gaslimit);
+        }
+    }
+}
@@ -1,6 +1,22 @@
 pub mod aliases;
 pub use self::aliases::*;
 
+pub mod analyzer;
+
+pub mod asm;
+
+pub mod blockstm;
+
+pub mod bloom;
+pub use self::bloom::*;
+
+#[cfg(test)]
+mod conservation;
+
+pub mod debugger;
+
+pub mod disasm;
+
 pub mod ethaccount;
 pub use self::ethaccount::*;
 
@@ -10,6 +26,9 @@ pub use self::ethblock::*;
 pub mod ethblockchain;
 pub use self::ethblockchain::*;
 
+pub mod ethreceipt;
+pub use self::ethreceipt::*;
+
 pub mod ethstate;
 pub use self::ethstate::*;
 
@@ -19,12 +38,43 @@ pub use self::ethtxn::*;
 pub mod evmstate;
 pub use self::evmstate::*;
 
+pub mod feemarket;
+pub use self::feemarket::*;
+
 pub mod gas;
 pub use self::gas::*;
 
+pub mod gasschedule;
+pub use self::gasschedule::*;
+
+pub mod hexutil;
+
+pub mod interpreter;
+pub use self::interpreter::*;
+
 pub mod ops;
 pub use self::ops::*;
 
+pub mod overlay;
+pub use self::overlay::*;
+
+pub mod parallel;
+
+pub mod rlp;
+pub use self::rlp::*;
+
+pub mod senders;
+pub use self::senders::*;
+
+pub mod threaded;
+pub use self::threaded::*;
+
+pub mod tracer;
+pub use self::tracer::*;
+
+pub mod txpool;
+pub use self::txpool::*;
+
 pub mod wei;
 pub use self::wei::*;
 
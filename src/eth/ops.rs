@@ -14,6 +14,8 @@ pub enum Op {
     ISZERO,
 
     POP,
+    MLOAD,
+    MSTORE,
     JUMP,
     JUMPI,
 
@@ -22,6 +24,14 @@ pub enum Op {
     ADDVAL,
     SUBVAL,
 
+    LOG0,
+    LOG1,
+    LOG2,
+    LOG3,
+    LOG4,
+
+    RETURN,
+
     INVALID(u8),
 }
 
@@ -40,6 +50,8 @@ impl Op {
             Op::ISZERO => gas::GVERYLOW,
 
             Op::POP => gas::GBASE,
+            Op::MLOAD => gas::GVERYLOW,
+            Op::MSTORE => gas::GVERYLOW,
             Op::JUMP => gas::GMID,
             Op::JUMPI => gas::GHIGH,
 
@@ -48,6 +60,12 @@ impl Op {
             Op::ADDVAL => gas::GBASE,
             Op::SUBVAL => gas::GBASE,
 
+            Op::LOG0 | Op::LOG1 | Op::LOG2 | Op::LOG3 | Op::LOG4 => {
+                gas::GLOG + gas::GLOGDATA + gas::GLOGTOPIC * self.log_topics() as Gas
+            }
+
+            Op::RETURN => gas::GZERO,
+
             Op::INVALID(_) => gas::GZERO,
         }
     }
@@ -66,6 +84,8 @@ impl Op {
             0x15 => Op::ISZERO,
 
             0x50 => Op::POP,
+            0x51 => Op::MLOAD,
+            0x52 => Op::MSTORE,
             0x56 => Op::JUMP,
             0x57 => Op::JUMPI,
 
@@ -74,10 +94,142 @@ impl Op {
             0xb1 => Op::ADDVAL,
             0xb2 => Op::SUBVAL,
 
+            0xa0 => Op::LOG0,
+            0xa1 => Op::LOG1,
+            0xa2 => Op::LOG2,
+            0xa3 => Op::LOG3,
+            0xa4 => Op::LOG4,
+
+            0xf3 => Op::RETURN,
+
             x => Op::INVALID(x),
         }
     }
 
+    pub fn to_byte(self) -> u8 {
+        match self {
+            Op::STOP => 0x00,
+            Op::ADD => 0x01,
+            Op::MUL => 0x02,
+            Op::SUB => 0x03,
+            Op::DIV => 0x04,
+
+            Op::LT => 0x10,
+            Op::GT => 0x11,
+            Op::EQ => 0x14,
+            Op::ISZERO => 0x15,
+
+            Op::POP => 0x50,
+            Op::MLOAD => 0x51,
+            Op::MSTORE => 0x52,
+            Op::JUMP => 0x56,
+            Op::JUMPI => 0x57,
+
+            Op::PUSH1(_) => 0x60,
+            Op::SETVAL => 0xb0,
+            Op::ADDVAL => 0xb1,
+            Op::SUBVAL => 0xb2,
+
+            Op::LOG0 => 0xa0,
+            Op::LOG1 => 0xa1,
+            Op::LOG2 => 0xa2,
+            Op::LOG3 => 0xa3,
+            Op::LOG4 => 0xa4,
+
+            Op::RETURN => 0xf3,
+
+            Op::INVALID(x) => x,
+        }
+    }
+
+    // inverse of from_bytes
+    pub fn to_bytes(ops: &[Op]) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(ops.len());
+        for op in ops {
+            bytes.push(op.to_byte());
+            if let Op::PUSH1(val) = op {
+                bytes.push(*val);
+            }
+        }
+        bytes
+    }
+
+    // the inverse of mnemonic; PUSH1 comes back with a zero operand
+    pub fn from_mnemonic(mnemonic: &str) -> Option<Op> {
+        (0..=255u8).map(Op::from_byte).find(|op| match op {
+            Op::INVALID(_) => false,
+            op => op.mnemonic() == mnemonic,
+        })
+    }
+
+    pub fn mnemonic(self) -> &'static str {
+        match self {
+            Op::STOP => "STOP",
+            Op::ADD => "ADD",
+            Op::MUL => "MUL",
+            Op::SUB => "SUB",
+            Op::DIV => "DIV",
+
+            Op::LT => "LT",
+            Op::GT => "GT",
+            Op::EQ => "EQ",
+            Op::ISZERO => "ISZERO",
+
+            Op::POP => "POP",
+            Op::MLOAD => "MLOAD",
+            Op::MSTORE => "MSTORE",
+            Op::JUMP => "JUMP",
+            Op::JUMPI => "JUMPI",
+
+            Op::PUSH1(_) => "PUSH1",
+            Op::SETVAL => "SETVAL",
+            Op::ADDVAL => "ADDVAL",
+            Op::SUBVAL => "SUBVAL",
+
+            Op::LOG0 => "LOG0",
+            Op::LOG1 => "LOG1",
+            Op::LOG2 => "LOG2",
+            Op::LOG3 => "LOG3",
+            Op::LOG4 => "LOG4",
+
+            Op::RETURN => "RETURN",
+
+            Op::INVALID(_) => "INVALID",
+        }
+    }
+
+    /// Returns how many items the op pops and then pushes.
+    pub fn stack_effect(self) -> (usize, usize) {
+        match self {
+            Op::STOP => (0, 0),
+            Op::ADD | Op::MUL | Op::SUB | Op::DIV => (2, 1),
+            Op::LT | Op::GT | Op::EQ => (2, 1),
+            Op::ISZERO => (1, 1),
+            Op::POP => (1, 0),
+            Op::MLOAD => (1, 1),
+            Op::MSTORE => (2, 0),
+            Op::JUMP => (1, 0),
+            Op::JUMPI => (2, 0),
+            Op::PUSH1(_) => (0, 1),
+            Op::SETVAL | Op::ADDVAL | Op::SUBVAL => (1, 0),
+            Op::LOG0 | Op::LOG1 | Op::LOG2 | Op::LOG3 | Op::LOG4 => (self.log_topics() + 1, 0),
+            Op::RETURN => (2, 0),
+            Op::INVALID(_) => (0, 0),
+        }
+    }
+
+    /// Returns the number of topics popped by a LOGn instruction, or 0 for
+    /// any other op.
+    pub fn log_topics(self) -> usize {
+        match self {
+            Op::LOG1 => 1,
+            Op::LOG2 => 2,
+            Op::LOG3 => 3,
+            Op::LOG4 => 4,
+            _ => 0,
+        }
+    }
+
     pub fn from_bytes(bytes: &[u8]) -> Vec<Op> {
         let mut ops = Vec::new();
         let mut iter = bytes.iter();
@@ -147,4 +299,30 @@ mod tests {
         let expected = vec![Op::LT, Op::PUSH1(0x10), Op::GT, Op::EQ];
         assert_eq!(compare_vecs(&actual, &expected), true);
     }
+
+    #[test]
+    fn test_to_bytes() {
+        let opcodes: Vec<u8> = vec![0x60, 0x10, 0x11, 0xa2, 0xb1, 0x05, 0x00];
+        assert_eq!(Op::to_bytes(&Op::from_bytes(&opcodes)), opcodes);
+        for byte in 0..=255u8 {
+            assert_eq!(Op::from_byte(byte).to_byte(), byte);
+        }
+    }
+
+    #[test]
+    fn test_logs() {
+        let opcodes: Vec<u8> = vec![0xa0, 0xa1, 0xa2, 0xa3, 0xa4, 0xa5];
+        let actual = Op::from_bytes(&opcodes);
+        let expected = vec![
+            Op::LOG0,
+            Op::LOG1,
+            Op::LOG2,
+            Op::LOG3,
+            Op::LOG4,
+            Op::INVALID(0xa5),
+        ];
+        assert!(compare_vecs(&actual, &expected));
+        assert_eq!(Op::LOG3.log_topics(), 3);
+        assert_eq!(Op::LOG0.to_cost() + 2 * 375, Op::LOG2.to_cost());
+    }
 }
@@ -0,0 +1,120 @@
+use std::collections::HashMap;
+
+use super::aliases::ETHAddress;
+use super::ethaccount::ETHAccount;
+use super::ethstate::{ETHState, StateAccess};
+use super::wei::Wei;
+
+/// A copy-on-write view of an `ETHState`. Reads fall through to the base
+/// state until an account is written, after which the overlay keeps its
+/// own copy. The base is never modified.
+pub struct StateOverlay<'a> {
+    base: &'a ETHState,
+    changes: HashMap<ETHAddress, ETHAccount>,
+}
+
+impl<'a> StateOverlay<'a> {
+    pub fn new(base: &'a ETHState) -> StateOverlay<'a> {
+        StateOverlay {
+            base,
+            changes: HashMap::new(),
+        }
+    }
+
+    pub fn get_account(&self, addr: &ETHAddress) -> Option<&ETHAccount> {
+        self.changes
+            .get(addr)
+            .or_else(|| self.base.get_account(addr))
+    }
+
+    // accounts written through the overlay
+    pub fn get_changes(&self) -> &HashMap<ETHAddress, ETHAccount> {
+        &self.changes
+    }
+
+    fn account_mut(&mut self, addr: &ETHAddress) -> Option<&mut ETHAccount> {
+        if !self.changes.contains_key(addr) {
+            let account = self.base.get_account(addr)?.clone();
+            self.changes.insert(*addr, account);
+        }
+        self.changes.get_mut(addr)
+    }
+}
+
+impl<'a> StateAccess for StateOverlay<'a> {
+    fn key_exists(&self, addr: &ETHAddress) -> bool {
+        self.get_account(addr).is_some()
+    }
+
+    fn get_value(&self, addr: &ETHAddress) -> Option<Wei> {
+        self.get_account(addr).map(|account| account.balance)
+    }
+
+    fn get_nonce(&self, addr: &ETHAddress) -> Option<u32> {
+        self.get_account(addr).map(|account| account.nonce)
+    }
+
+    fn increment_nonce(&mut self, addr: &ETHAddress) {
+        self.account_mut(addr).unwrap().nonce += 1;
+    }
+
+    fn safe_deduct(&mut self, addr: &ETHAddress, amount: Wei) -> bool {
+        match self.account_mut(addr) {
+            Some(account) => match account.balance - amount {
+                Some(val) => {
+                    account.balance = val;
+                    true
+                }
+                None => false,
+            },
+            None => false,
+        }
+    }
+
+    fn pay(&mut self, addr: &ETHAddress, amount: Wei) {
+        if self.account_mut(addr).is_none() {
+            self.changes.insert(*addr, ETHAccount::new(*addr));
+        }
+        self.changes.get_mut(addr).unwrap().balance += amount;
+    }
+
+    fn get_code(&self, addr: &ETHAddress) -> Option<&[u8]> {
+        self.get_account(addr).map(|account| &account.code[..])
+    }
+
+    fn set_code(&mut self, addr: &ETHAddress, code: Vec<u8>) {
+        if self.account_mut(addr).is_none() {
+            self.changes.insert(*addr, ETHAccount::new(*addr));
+        }
+        self.changes.get_mut(addr).unwrap().code = code;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        super::ethstate::ETHState, super::ethstate::StateAccess, super::wei::Wei, StateOverlay,
+    };
+
+    #[test]
+    fn writes_stay_in_the_overlay() {
+        let mut base = ETHState::default();
+        base.pay(&[1; 20], Wei::from_wei(50));
+
+        let mut overlay = StateOverlay::new(&base);
+        assert!(overlay.safe_deduct(&[1; 20], Wei::from_wei(20)));
+        assert!(!overlay.safe_deduct(&[1; 20], Wei::from_wei(31)));
+        overlay.increment_nonce(&[1; 20]);
+        overlay.pay(&[2; 20], Wei::from_wei(5));
+        assert!(!overlay.safe_deduct(&[3; 20], Wei::from_wei(0)));
+
+        assert_eq!(overlay.get_value(&[1; 20]), Some(Wei::from_wei(30)));
+        assert_eq!(overlay.get_nonce(&[1; 20]), Some(1));
+        assert_eq!(overlay.get_value(&[2; 20]), Some(Wei::from_wei(5)));
+        assert_eq!(overlay.get_changes().len(), 2);
+
+        assert_eq!(base.get_value(&[1; 20]), Some(Wei::from_wei(50)));
+        assert_eq!(base.get_nonce(&[1; 20]), Some(0));
+        assert!(!base.key_exists(&[2; 20]));
+    }
+}
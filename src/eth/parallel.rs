@@ -0,0 +1,49 @@
+use std::thread;
+
+/// Maps `f` over `items` across `num_threads` threads, keeping the results
+/// in the order of `items`.
+pub fn map_chunks<T, R, F>(items: &[T], num_threads: usize, f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    // `chunks` can't make chunks of nothing
+    if items.is_empty() {
+        return vec![];
+    }
+    // one contiguous chunk per thread keeps the results in order
+    let chunk_size = items.len().div_ceil(num_threads.max(1));
+    let f = &f;
+    thread::scope(|scope| {
+        let handles: Vec<_> = items
+            .chunks(chunk_size)
+            .map(|chunk| scope.spawn(move || chunk.iter().map(f).collect::<Vec<_>>()))
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap())
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::map_chunks;
+
+    #[test]
+    fn keeps_the_order_of_its_items() {
+        let items: Vec<u32> = (0..10).collect();
+        let squares: Vec<u32> = items.iter().map(|item| item * item).collect();
+        // including more threads than items, and none at all
+        for num_threads in 0..=12 {
+            assert_eq!(map_chunks(&items, num_threads, |item| item * item), squares);
+        }
+        assert!(map_chunks(&[] as &[u32], 4, |item| *item).is_empty());
+
+        let ids = map_chunks(&items, 2, |_| thread::current().id());
+        assert_ne!(ids[0], ids[9]);
+    }
+}
@@ -0,0 +1,291 @@
+// Ethereum's recursive length prefix encoding, as in the yellow paper's
+// appendix B.
+
+// items up to this long have their length in the prefix byte
+const SHORT_LENGTH: usize = 55;
+const BYTES_OFFSET: u8 = 0x80;
+const LIST_OFFSET: u8 = 0xc0;
+// lists nested deeper than this are rejected rather than decoded
+// recursively, which could overflow the stack
+const MAX_DEPTH: usize = 64;
+
+/// What transactions and blocks are serialized as before they're hashed and
+/// signed. Bincode is GadgetCoin's own format; RLP is what Ethereum tools
+/// understand.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum HashEncoding {
+    #[default]
+    Bincode,
+    Rlp,
+}
+
+/// An RLP item: a byte string, or a list of items.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Rlp {
+    Bytes(Vec<u8>),
+    List(Vec<Rlp>),
+}
+
+impl Rlp {
+    pub fn bytes(bytes: &[u8]) -> Rlp {
+        Rlp::Bytes(bytes.to_vec())
+    }
+
+    /// A big-endian integer without leading zeros, so zero is empty.
+    pub fn uint(value: u128) -> Rlp {
+        Rlp::uint_from_be(&value.to_be_bytes())
+    }
+
+    /// Like `uint`, for integers too wide for u128, such as signature
+    /// scalars.
+    pub fn uint_from_be(bytes: &[u8]) -> Rlp {
+        let zeros = bytes.iter().take_while(|&&byte| byte == 0).count();
+        Rlp::bytes(&bytes[zeros..])
+    }
+
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            Rlp::Bytes(bytes) => Some(bytes),
+            Rlp::List(_) => None,
+        }
+    }
+
+    pub fn as_list(&self) -> Option<&[Rlp]> {
+        match self {
+            Rlp::Bytes(_) => None,
+            Rlp::List(items) => Some(items),
+        }
+    }
+
+    /// The integer a byte string holds, if it fits and has no leading zeros.
+    pub fn as_uint(&self) -> Option<u128> {
+        let bytes = self.as_bytes()?;
+        if bytes.len() > 16 || bytes.first() == Some(&0) {
+            return None;
+        }
+        Some(
+            bytes
+                .iter()
+                .fold(0, |value, &byte| value << 8 | u128::from(byte)),
+        )
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = vec![];
+        self.encode_into(&mut out);
+        out
+    }
+
+    fn encode_into(&self, out: &mut Vec<u8>) {
+        match self {
+            Rlp::Bytes(bytes) => {
+                // a lone byte below the prefixes is its own encoding
+                if bytes.len() == 1 && bytes[0] < BYTES_OFFSET {
+                    out.push(bytes[0]);
+                } else {
+                    encode_length(bytes.len(), BYTES_OFFSET, out);
+                    out.extend_from_slice(bytes);
+                }
+            }
+            Rlp::List(items) => {
+                let mut payload = vec![];
+                for item in items {
+                    item.encode_into(&mut payload);
+                }
+                encode_length(payload.len(), LIST_OFFSET, out);
+                out.extend(payload);
+            }
+        }
+    }
+
+    /// Decodes exactly one item spanning all of `input`. Only the shortest
+    /// encoding of an item is accepted, so each item has just one.
+    pub fn decode(input: &[u8]) -> Result<Rlp, String> {
+        let (item, rest) = decode_item(input, 0)?;
+        if !rest.is_empty() {
+            return Err(format!("{} trailing bytes", rest.len()));
+        }
+        Ok(item)
+    }
+}
+
+fn encode_length(len: usize, offset: u8, out: &mut Vec<u8>) {
+    if len <= SHORT_LENGTH {
+        out.push(offset + len as u8);
+    } else {
+        let bytes = len.to_be_bytes();
+        let zeros = bytes.iter().take_while(|&&byte| byte == 0).count();
+        out.push(offset + SHORT_LENGTH as u8 + (bytes.len() - zeros) as u8);
+        out.extend_from_slice(&bytes[zeros..]);
+    }
+}
+
+// splits the first item off `input`, which is inside `depth` lists
+fn decode_item(input: &[u8], depth: usize) -> Result<(Rlp, &[u8]), String> {
+    let prefix = *input.first().ok_or("unexpected end of input")?;
+    if prefix < BYTES_OFFSET {
+        return Ok((Rlp::bytes(&input[..1]), &input[1..]));
+    }
+    let (offset, is_list) = if prefix < LIST_OFFSET {
+        (BYTES_OFFSET, false)
+    } else {
+        (LIST_OFFSET, true)
+    };
+    let (len, header) = decode_length(input, prefix - offset)?;
+    let end = header.checked_add(len).ok_or("length overflows")?;
+    if end > input.len() {
+        return Err(format!(
+            "item needs {} bytes but {} remain",
+            end,
+            input.len()
+        ));
+    }
+    let (payload, rest) = (&input[header..end], &input[end..]);
+    if !is_list {
+        if len == 1 && payload[0] < BYTES_OFFSET {
+            return Err(format!("byte 0x{:02x} should encode itself", payload[0]));
+        }
+        return Ok((Rlp::bytes(payload), rest));
+    }
+    if depth == MAX_DEPTH {
+        return Err(format!("lists nested more than {} deep", MAX_DEPTH));
+    }
+    let mut items = vec![];
+    let mut payload = payload;
+    while !payload.is_empty() {
+        let (item, remaining) = decode_item(payload, depth + 1)?;
+        items.push(item);
+        payload = remaining;
+    }
+    Ok((Rlp::List(items), rest))
+}
+
+// the payload length `code` (the prefix minus its offset) gives, along with
+// how many bytes of `input` the prefix takes up
+fn decode_length(input: &[u8], code: u8) -> Result<(usize, usize), String> {
+    let code = code as usize;
+    if code <= SHORT_LENGTH {
+        return Ok((code, 1));
+    }
+    let len_bytes = code - SHORT_LENGTH;
+    let bytes = input
+        .get(1..1 + len_bytes)
+        .ok_or("unexpected end of input")?;
+    if bytes[0] == 0 {
+        return Err("length has leading zeros".to_string());
+    }
+    if len_bytes > std::mem::size_of::<usize>() {
+        return Err("length overflows".to_string());
+    }
+    let len = bytes
+        .iter()
+        .fold(0, |len, &byte| len << 8 | usize::from(byte));
+    if len <= SHORT_LENGTH {
+        return Err(format!("length {} should be in the prefix", len));
+    }
+    Ok((len, 1 + len_bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+
+    use super::{super::hexutil, encode_length, Rlp, LIST_OFFSET, MAX_DEPTH};
+
+    fn string(s: &str) -> Rlp {
+        Rlp::bytes(s.as_bytes())
+    }
+
+    // the examples from appendix B of the yellow paper and the Ethereum wiki
+    #[test]
+    fn yellow_paper_vectors() {
+        let lorem = "Lorem ipsum dolor sit amet, consectetur adipisicing elit";
+        let set = |items: Vec<Rlp>| Rlp::List(items);
+        let vectors = vec![
+            (string("dog"), "83646f67".to_string()),
+            (
+                set(vec![string("cat"), string("dog")]),
+                "c88363617483646f67".to_string(),
+            ),
+            (string(""), "80".to_string()),
+            (set(vec![]), "c0".to_string()),
+            (Rlp::uint(0), "80".to_string()),
+            (Rlp::bytes(&[0]), "00".to_string()),
+            (Rlp::uint(15), "0f".to_string()),
+            (Rlp::uint(1024), "820400".to_string()),
+            // the set theoretical representation of three
+            (
+                set(vec![
+                    set(vec![]),
+                    set(vec![set(vec![])]),
+                    set(vec![set(vec![]), set(vec![set(vec![])])]),
+                ]),
+                "c7c0c1c0c3c0c1c0".to_string(),
+            ),
+            (
+                string(lorem),
+                format!("b838{}", hexutil::encode(lorem.as_bytes())),
+            ),
+        ];
+        for (item, hex) in vectors {
+            let encoded = hexutil::decode(&hex).unwrap();
+            assert_eq!(item.encode(), encoded, "{:?}", item);
+            assert_eq!(Rlp::decode(&encoded), Ok(item));
+        }
+    }
+
+    #[test]
+    fn long_lists_and_integers() {
+        let long = Rlp::List(vec![Rlp::bytes(&[0xaa; 40]), Rlp::bytes(&[0xbb; 40])]);
+        let encoded = long.encode();
+        assert_eq!(&encoded[..2], &[0xf8, 82]);
+        assert_eq!(Rlp::decode(&encoded), Ok(long));
+
+        assert_eq!(Rlp::uint(u128::MAX).as_uint(), Some(u128::MAX));
+        assert_eq!(Rlp::uint(0).as_uint(), Some(0));
+        assert_eq!(Rlp::bytes(&[0, 1]).as_uint(), None);
+        assert_eq!(Rlp::bytes(&[1; 17]).as_uint(), None);
+        assert_eq!(Rlp::List(vec![]).as_uint(), None);
+    }
+
+    #[test]
+    fn rejects_malformed_input() {
+        for hex in &[
+            // empty, truncated, or followed by more
+            "",
+            "83646f",
+            "c883636174",
+            "83646f6700",
+            // a byte that encodes itself, wrapped anyway
+            "8105",
+            // a long-form length that fits in the prefix, or has a leading zero
+            "b80161",
+            "b90038",
+        ] {
+            let encoded = hexutil::decode(hex).unwrap();
+            assert!(Rlp::decode(&encoded).is_err(), "{}", hex);
+        }
+    }
+
+    #[test]
+    fn rejects_deep_nesting() {
+        let mut item = Rlp::List(vec![]);
+        for _ in 1..MAX_DEPTH {
+            item = Rlp::List(vec![item]);
+        }
+        assert_eq!(Rlp::decode(&item.encode()), Ok(item.clone()));
+        let item = Rlp::List(vec![item]);
+        assert!(Rlp::decode(&item.encode()).is_err());
+
+        // far too deep to decode recursively
+        let mut encoded = VecDeque::from(vec![LIST_OFFSET]);
+        for _ in 0..100_000 {
+            let mut prefix = vec![];
+            encode_length(encoded.len(), LIST_OFFSET, &mut prefix);
+            for &byte in prefix.iter().rev() {
+                encoded.push_front(byte);
+            }
+        }
+        assert!(Rlp::decode(encoded.make_contiguous()).is_err());
+    }
+}
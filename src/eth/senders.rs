@@ -0,0 +1,55 @@
+use super::aliases::ETHAddress;
+use super::ethtxn::ETHTxn;
+use super::parallel;
+
+/// Recovers the sender of each of `txns`, in order, splitting the work
+/// between `num_threads` threads. A transaction whose signature recovers to
+/// nothing gets `None`.
+pub fn recover_senders(txns: &[ETHTxn], num_threads: usize) -> Vec<Option<ETHAddress>> {
+    parallel::map_chunks(txns, num_threads, |txn| txn.get_sender_addr().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        super::ethtxn::utils::get_bs_ecsda_field, super::ethtxn::ETHTxn,
+        super::ethtxn::DEFAULT_CHAIN_ID, super::rlp::HashEncoding, super::wei::Wei,
+        recover_senders,
+    };
+
+    #[test]
+    fn matches_serial_recovery() {
+        let keys: Vec<_> = (1..=3)
+            .map(|seed| secp256k1::SecretKey::parse(&[seed; 32]).unwrap())
+            .collect();
+        let mut txns = vec![];
+        for nonce in 1..=10 {
+            let key = &keys[nonce as usize % keys.len()];
+            let mut txn = ETHTxn {
+                nonce,
+                chain_id: DEFAULT_CHAIN_ID,
+                gasprice: Wei::from_wei(1),
+                max_fee: None,
+                max_priority_fee: None,
+                gaslimit: 10,
+                recipient: Some([7; 20]),
+                value: Wei::from_wei(1),
+                code: vec![],
+                encoding: HashEncoding::Bincode,
+                ecdsa_fields: get_bs_ecsda_field(key),
+            };
+            txn.sign_transaction(key);
+            txns.push(txn);
+        }
+        // a high S never recovers
+        txns[4].ecdsa_fields.0.s = txns[4].ecdsa_fields.0.s.neg();
+
+        let serial: Vec<_> = txns.iter().map(|txn| txn.get_sender_addr().ok()).collect();
+        assert_eq!(serial[4], None);
+        // including more threads than transactions, and none at all
+        for num_threads in 0..=12 {
+            assert_eq!(recover_senders(&txns, num_threads), serial);
+        }
+        assert!(recover_senders(&[], 4).is_empty());
+    }
+}
@@ -0,0 +1,327 @@
+use std::sync::Arc;
+
+use super::evmexec::{LogEntry, MEMORY_SIZE};
+use super::gas::Gas;
+use super::gasschedule::GasSchedule;
+use super::interpreter::{ExecutionResult, Interpreter};
+use super::ops::Op;
+use super::wei::Wei;
+
+/// A backend that compiles code into a table of handler functions before
+/// running it, so no op is decoded or matched on while executing.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ThreadedInterpreter;
+
+impl Interpreter for ThreadedInterpreter {
+    fn execute(
+        &self,
+        gaslimit: Gas,
+        code: &[Op],
+        txn_value: Wei,
+        schedule: &Arc<GasSchedule>,
+    ) -> ExecutionResult {
+        ThreadedProgram::compile(code, schedule).run(gaslimit, txn_value)
+    }
+}
+
+// Ok(true) to keep going, Ok(false) on STOP, Err on abnormal termination
+type Handler = fn(&mut Machine, &Instr) -> Result<bool, ()>;
+
+#[derive(Clone, Copy)]
+struct Instr {
+    handler: Handler,
+    arg: u8,
+    cost: Gas,
+    // for a PUSH1 fused with the jump after it, what the jump costs
+    jump_cost: Gas,
+}
+
+/// Code compiled for `ThreadedInterpreter`. It can be run any number of
+/// times without recompiling.
+pub struct ThreadedProgram {
+    // one per op, so jump targets index it directly
+    instrs: Vec<Instr>,
+}
+
+impl ThreadedProgram {
+    pub fn compile(code: &[Op], schedule: &GasSchedule) -> ThreadedProgram {
+        let mut instrs: Vec<Instr> = code
+            .iter()
+            .map(|&op| Instr {
+                handler: handler(op),
+                arg: match op {
+                    Op::PUSH1(val) => val,
+                    op => op.log_topics() as u8,
+                },
+                cost: schedule.cost(op),
+                jump_cost: 0,
+            })
+            .collect();
+
+        // PUSH1 followed by a jump becomes one instruction with the target
+        // baked in. The jump keeps its own instruction too, since other
+        // code may land on it with a different target on the stack.
+        for i in 1..code.len() {
+            if let Op::PUSH1(_) = code[i - 1] {
+                let fused: Handler = match code[i] {
+                    Op::JUMP => push_jump,
+                    Op::JUMPI => push_jumpi,
+                    _ => continue,
+                };
+                instrs[i - 1].handler = fused;
+                instrs[i - 1].jump_cost = instrs[i].cost;
+            }
+        }
+        ThreadedProgram { instrs }
+    }
+
+    pub fn run(&self, gaslimit: Gas, txn_value: Wei) -> ExecutionResult {
+        let mut machine = Machine {
+            stack: Vec::new(),
+            memory: [0; MEMORY_SIZE],
+            pc: 0,
+            gas_left: gaslimit,
+            value: txn_value,
+            logs: Vec::new(),
+            output: Vec::new(),
+        };
+        let succeeded = self.instrs.is_empty() || self.dispatch(&mut machine);
+        ExecutionResult {
+            succeeded,
+            gas_left: machine.gas_left,
+            value: machine.value,
+            logs: machine.logs,
+            stack: machine.stack,
+            output: machine.output,
+        }
+    }
+
+    fn dispatch(&self, machine: &mut Machine) -> bool {
+        loop {
+            let instr = match self.instrs.get(machine.pc) {
+                Some(instr) => instr,
+                // pc out of bounds
+                None => return false,
+            };
+            match (instr.handler)(machine, instr) {
+                Ok(true) => {}
+                Ok(false) => return true,
+                Err(()) => return false,
+            }
+        }
+    }
+}
+
+struct Machine {
+    stack: Vec<u8>,
+    memory: [u8; MEMORY_SIZE],
+    pc: usize,
+    gas_left: Gas,
+    value: Wei,
+    logs: Vec<LogEntry>,
+    output: Vec<u8>,
+}
+
+impl Machine {
+    fn pop(&mut self) -> Result<u8, ()> {
+        self.stack.pop().ok_or(())
+    }
+
+    // like ExecutionContext, an op's effects land before its gas is charged
+    fn charge(&mut self, cost: Gas) -> Result<(), ()> {
+        self.gas_left = self.gas_left.checked_sub(cost).ok_or(())?;
+        Ok(())
+    }
+
+    fn advance(&mut self, instr: &Instr) -> Result<bool, ()> {
+        self.pc += 1;
+        self.charge(instr.cost)?;
+        Ok(true)
+    }
+
+    fn jump_to(&mut self, target: u8, cost: Gas) -> Result<bool, ()> {
+        self.pc = usize::from(target);
+        self.charge(cost)?;
+        Ok(true)
+    }
+
+    fn binary(&mut self, instr: &Instr, f: fn(u8, u8) -> u8) -> Result<bool, ()> {
+        let a = self.pop()?;
+        let b = self.pop()?;
+        self.stack.push(f(a, b));
+        self.advance(instr)
+    }
+}
+
+fn handler(op: Op) -> Handler {
+    match op {
+        Op::STOP => stop,
+        Op::ADD => add,
+        Op::MUL => mul,
+        Op::SUB => sub,
+        Op::DIV => div,
+        Op::LT => lt,
+        Op::GT => gt,
+        Op::EQ => eq,
+        Op::ISZERO => iszero,
+        Op::POP => pop,
+        Op::MLOAD => mload,
+        Op::MSTORE => mstore,
+        Op::JUMP => jump,
+        Op::JUMPI => jumpi,
+        Op::PUSH1(_) => push1,
+        Op::SETVAL => setval,
+        Op::ADDVAL => addval,
+        Op::SUBVAL => subval,
+        Op::LOG0 | Op::LOG1 | Op::LOG2 | Op::LOG3 | Op::LOG4 => log,
+        Op::RETURN => ret,
+        Op::INVALID(_) => invalid,
+    }
+}
+
+fn stop(_: &mut Machine, _: &Instr) -> Result<bool, ()> {
+    Ok(false)
+}
+
+fn add(m: &mut Machine, instr: &Instr) -> Result<bool, ()> {
+    m.binary(instr, |a, b| a.wrapping_add(b))
+}
+
+fn mul(m: &mut Machine, instr: &Instr) -> Result<bool, ()> {
+    m.binary(instr, |a, b| a.wrapping_mul(b))
+}
+
+fn sub(m: &mut Machine, instr: &Instr) -> Result<bool, ()> {
+    m.binary(instr, |a, b| a.saturating_sub(b))
+}
+
+fn div(m: &mut Machine, instr: &Instr) -> Result<bool, ()> {
+    m.binary(instr, |a, b| a.checked_div(b).unwrap_or(0))
+}
+
+fn lt(m: &mut Machine, instr: &Instr) -> Result<bool, ()> {
+    m.binary(instr, |a, b| (a < b) as u8)
+}
+
+fn gt(m: &mut Machine, instr: &Instr) -> Result<bool, ()> {
+    m.binary(instr, |a, b| (a > b) as u8)
+}
+
+fn eq(m: &mut Machine, instr: &Instr) -> Result<bool, ()> {
+    m.binary(instr, |a, b| (a == b) as u8)
+}
+
+fn iszero(m: &mut Machine, instr: &Instr) -> Result<bool, ()> {
+    let a = m.pop()?;
+    m.stack.push((a == 0) as u8);
+    m.advance(instr)
+}
+
+fn pop(m: &mut Machine, instr: &Instr) -> Result<bool, ()> {
+    m.pop()?;
+    m.advance(instr)
+}
+
+fn mload(m: &mut Machine, instr: &Instr) -> Result<bool, ()> {
+    let addr = m.pop()?;
+    let val = m.memory[usize::from(addr)];
+    m.stack.push(val);
+    m.advance(instr)
+}
+
+fn mstore(m: &mut Machine, instr: &Instr) -> Result<bool, ()> {
+    let addr = m.pop()?;
+    let val = m.pop()?;
+    m.memory[usize::from(addr)] = val;
+    m.advance(instr)
+}
+
+fn jump(m: &mut Machine, instr: &Instr) -> Result<bool, ()> {
+    let target = m.pop()?;
+    m.jump_to(target, instr.cost)
+}
+
+fn jumpi(m: &mut Machine, instr: &Instr) -> Result<bool, ()> {
+    let target = m.pop()?;
+    let cond = m.pop()?;
+    if cond != 0 {
+        m.jump_to(target, instr.cost)
+    } else {
+        m.advance(instr)
+    }
+}
+
+// charges for the PUSH1 half of a fused pair
+fn fused_push(m: &mut Machine, instr: &Instr) -> Result<(), ()> {
+    m.pc += 1;
+    if let Err(()) = m.charge(instr.cost) {
+        // the push happened but the jump never runs
+        m.stack.push(instr.arg);
+        return Err(());
+    }
+    Ok(())
+}
+
+// PUSH1 target; JUMP without the round trip through the stack
+fn push_jump(m: &mut Machine, instr: &Instr) -> Result<bool, ()> {
+    fused_push(m, instr)?;
+    m.jump_to(instr.arg, instr.jump_cost)
+}
+
+fn push_jumpi(m: &mut Machine, instr: &Instr) -> Result<bool, ()> {
+    fused_push(m, instr)?;
+    let cond = m.pop()?;
+    if cond != 0 {
+        m.jump_to(instr.arg, instr.jump_cost)
+    } else {
+        m.pc += 1;
+        m.charge(instr.jump_cost)?;
+        Ok(true)
+    }
+}
+
+fn push1(m: &mut Machine, instr: &Instr) -> Result<bool, ()> {
+    m.stack.push(instr.arg);
+    m.advance(instr)
+}
+
+fn setval(m: &mut Machine, instr: &Instr) -> Result<bool, ()> {
+    let a = m.pop()?;
+    m.value = Wei::from_wei(a.into());
+    m.advance(instr)
+}
+
+fn addval(m: &mut Machine, instr: &Instr) -> Result<bool, ()> {
+    let a = m.pop()?;
+    m.value = m.value.saturating_add(Wei::from_wei(a.into()));
+    m.advance(instr)
+}
+
+fn subval(m: &mut Machine, instr: &Instr) -> Result<bool, ()> {
+    let a = m.pop()?;
+    m.value = (m.value - Wei::from_wei(a.into())).unwrap_or_default();
+    m.advance(instr)
+}
+
+fn log(m: &mut Machine, instr: &Instr) -> Result<bool, ()> {
+    let data = m.pop()?;
+    let num_topics = usize::from(instr.arg);
+    let mut topics = Vec::with_capacity(num_topics);
+    for _ in 0..num_topics {
+        topics.push(m.pop()?);
+    }
+    m.logs.push(LogEntry { topics, data });
+    m.advance(instr)
+}
+
+// like STOP, it isn't charged
+fn ret(m: &mut Machine, _: &Instr) -> Result<bool, ()> {
+    let offset = usize::from(m.pop()?);
+    let len = usize::from(m.pop()?);
+    m.output = m.memory.get(offset..offset + len).ok_or(())?.to_vec();
+    Ok(false)
+}
+
+fn invalid(_: &mut Machine, _: &Instr) -> Result<bool, ()> {
+    Err(())
+}
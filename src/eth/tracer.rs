@@ -0,0 +1,136 @@
+use std::io;
+use std::io::Write;
+
+use super::gas::Gas;
+use super::ops::Op;
+use super::wei::Wei;
+
+/// A snapshot of the machine around a single op.
+#[derive(Debug)]
+pub struct StepInfo<'a> {
+    pub pc: u32,
+    pub op: Op,
+    // what the op costs under the context's gas schedule
+    pub gas_cost: Gas,
+    pub stack: &'a [u8],
+    pub gas_left: Gas,
+    pub value: Wei,
+}
+
+/// Hooks called by `ExecutionContext` around every executed op.
+pub trait Tracer {
+    /// Called before `step.op` runs.
+    fn before_op(&mut self, _step: &StepInfo) {}
+
+    /// Called after `step.op` has run (or failed), with the updated machine.
+    fn after_op(&mut self, _step: &StepInfo, _succeeded: bool) {}
+}
+
+/// The default tracer; its empty hooks are inlined away.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NoopTracer;
+
+impl Tracer for NoopTracer {}
+
+/// Emits one JSON object per step, modelled on geth's `structLog` output.
+pub struct JsonTracer<W: Write> {
+    out: W,
+    error: Option<io::Error>,
+    pending: Option<String>,
+}
+
+impl<W: Write> JsonTracer<W> {
+    pub fn new(out: W) -> JsonTracer<W> {
+        JsonTracer {
+            out,
+            error: None,
+            pending: None,
+        }
+    }
+
+    /// Returns the underlying writer, or the first write error encountered.
+    pub fn finish(self) -> io::Result<W> {
+        match self.error {
+            Some(err) => Err(err),
+            None => Ok(self.out),
+        }
+    }
+
+    fn write_line(&mut self, line: &str) {
+        if self.error.is_none() {
+            if let Err(err) = writeln!(self.out, "{}", line) {
+                self.error = Some(err);
+            }
+        }
+    }
+}
+
+impl<W: Write> Tracer for JsonTracer<W> {
+    fn before_op(&mut self, step: &StepInfo) {
+        let stack: Vec<String> = step
+            .stack
+            .iter()
+            .map(|item| format!("\"0x{:x}\"", item))
+            .collect();
+        // written out once the step's outcome is known
+        self.pending = Some(format!(
+            "{{\"pc\":{},\"op\":\"{}\",\"gas\":{},\"gasCost\":{},\"depth\":1,\"stack\":[{}],\"value\":{}",
+            step.pc,
+            step.op.mnemonic(),
+            step.gas_left,
+            step.gas_cost,
+            stack.join(","),
+            step.value.get_wei()
+        ));
+    }
+
+    fn after_op(&mut self, _step: &StepInfo, succeeded: bool) {
+        if let Some(mut line) = self.pending.take() {
+            if !succeeded {
+                line.push_str(",\"error\":\"execution failed\"");
+            }
+            line.push('}');
+            self.write_line(&line);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{super::evmexec::ExecutionContext, super::ops::Op::*, super::wei::Wei, JsonTracer};
+
+    #[test]
+    fn json_tracer_struct_logs() {
+        let ops = vec![PUSH1(2), PUSH1(3), ADD, SETVAL, STOP];
+        let mut engine =
+            ExecutionContext::with_tracer(100, ops, Wei::from_wei(1), JsonTracer::new(vec![]));
+        assert!(engine.finish_executing());
+        let output = String::from_utf8(engine.into_tracer().finish().unwrap()).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 5);
+        assert_eq!(
+            lines[0],
+            "{\"pc\":0,\"op\":\"PUSH1\",\"gas\":100,\"gasCost\":3,\"depth\":1,\"stack\":[],\"value\":1}"
+        );
+        assert_eq!(
+            lines[2],
+            "{\"pc\":2,\"op\":\"ADD\",\"gas\":94,\"gasCost\":3,\"depth\":1,\"stack\":[\"0x2\",\"0x3\"],\"value\":1}"
+        );
+        assert!(lines[4].starts_with("{\"pc\":4,\"op\":\"STOP\""));
+    }
+
+    #[test]
+    fn json_tracer_reports_failure() {
+        let mut engine = ExecutionContext::with_tracer(
+            100,
+            vec![PUSH1(1), ADD],
+            Wei::from_wei(0),
+            JsonTracer::new(vec![]),
+        );
+        assert!(!engine.finish_executing());
+        let output = String::from_utf8(engine.into_tracer().finish().unwrap()).unwrap();
+        let last = output.lines().last().unwrap();
+        assert!(last.starts_with("{\"pc\":1,\"op\":\"ADD\""));
+        assert!(last.ends_with(",\"error\":\"execution failed\"}"));
+    }
+}
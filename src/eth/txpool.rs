@@ -0,0 +1,586 @@
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BinaryHeap, HashMap};
+
+use super::aliases::ETHAddress;
+use super::ethtxn::ETHTxn;
+use super::gas::Gas;
+use super::wei::Wei;
+
+pub const DEFAULT_POOL_SIZE: usize = 4096;
+pub const DEFAULT_SENDER_LIMIT: usize = 64;
+// how much more, in percent, a replacement has to pay per unit of gas
+pub const DEFAULT_PRICE_BUMP: u32 = 10;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum PoolError {
+    // the sender has already used this nonce
+    StaleNonce,
+    // this exact transaction is already waiting
+    AlreadyKnown,
+    // a transaction with this sender and nonce is already waiting, and the
+    // new one doesn't pay enough more to replace it
+    ReplacementUnderpriced,
+    SenderFull,
+    // full, and nothing in the pool tips less than the new transaction
+    PoolFull,
+}
+
+struct PooledTxn {
+    txn: ETHTxn,
+    // arrival order, which breaks gasprice ties
+    seq: u64,
+}
+
+/// Transactions waiting to go into a block. Each sender's transactions are
+/// pending once every nonce before them is filled, and queued until then.
+pub struct TxPool {
+    pending: HashMap<ETHAddress, BTreeMap<u32, PooledTxn>>,
+    queued: HashMap<ETHAddress, BTreeMap<u32, PooledTxn>>,
+    max_size: usize,
+    max_per_sender: usize,
+    price_bump: u32,
+    len: usize,
+    next_seq: u64,
+}
+
+impl TxPool {
+    pub fn new(max_size: usize, max_per_sender: usize) -> TxPool {
+        TxPool {
+            pending: HashMap::new(),
+            queued: HashMap::new(),
+            max_size,
+            max_per_sender,
+            price_bump: DEFAULT_PRICE_BUMP,
+            len: 0,
+            next_seq: 0,
+        }
+    }
+
+    pub fn set_limits(&mut self, max_size: usize, max_per_sender: usize) {
+        self.max_size = max_size;
+        self.max_per_sender = max_per_sender;
+    }
+
+    // a replacement's max fee and max priority fee must both be at least
+    // `percent`% higher, and higher at all
+    pub fn set_price_bump(&mut self, percent: u32) {
+        self.price_bump = percent;
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn pending_len(&self) -> usize {
+        self.pending.values().map(BTreeMap::len).sum()
+    }
+
+    pub fn queued_len(&self) -> usize {
+        self.queued.values().map(BTreeMap::len).sum()
+    }
+
+    // the sum of the gas limits of the pending transactions a block with
+    // `base_fee` could take, stopping at each sender's first that can't pay it
+    pub fn pending_gas(&self, base_fee: Wei) -> Gas {
+        self.pending
+            .values()
+            .flat_map(|txns| {
+                txns.values()
+                    .take_while(|pooled| pooled.txn.effective_gasprice(base_fee).is_some())
+            })
+            .map(|pooled| pooled.txn.gaslimit)
+            .sum()
+    }
+
+    /// Adds a transaction from `sender`, whose last used nonce in the state
+    /// is `state_nonce`. A full pool makes room by evicting the transaction
+    /// that would tip the least at `base_fee`, the same order blocks are
+    /// built in, if that tips less than the new one. A transaction with the
+    /// same sender and nonce as one already waiting replaces it if it pays
+    /// enough more.
+    pub fn add(
+        &mut self,
+        txn: ETHTxn,
+        sender: ETHAddress,
+        state_nonce: u32,
+        base_fee: Wei,
+    ) -> Result<(), PoolError> {
+        if txn.nonce <= state_nonce {
+            return Err(PoolError::StaleNonce);
+        }
+        let price_bump = u128::from(self.price_bump);
+        let seq = self.next_seq;
+        if let Some(pooled) = self.get_mut(&sender, txn.nonce) {
+            if pooled.txn.hash() == txn.hash() {
+                return Err(PoolError::AlreadyKnown);
+            }
+            // strictly more, so a zero fee can't be replaced by another
+            let bumped = |new: Wei, old: Wei| {
+                new > old && new.saturating_mul(100) >= old.saturating_mul(100 + price_bump)
+            };
+            if !bumped(txn.get_max_fee(), pooled.txn.get_max_fee())
+                || !bumped(
+                    txn.get_max_priority_fee(),
+                    pooled.txn.get_max_priority_fee(),
+                )
+            {
+                return Err(PoolError::ReplacementUnderpriced);
+            }
+            // the replacement takes the old one's place
+            *pooled = PooledTxn { txn, seq };
+            self.next_seq += 1;
+            return Ok(());
+        }
+        if self.sender_len(&sender) >= self.max_per_sender {
+            return Err(PoolError::SenderFull);
+        }
+        if self.len >= self.max_size {
+            let rank = eviction_rank(&txn, base_fee);
+            let (victim, nonce) = match self.cheapest_evictable(&sender, txn.nonce, base_fee) {
+                Some((addr, nonce, victim_rank)) if victim_rank < rank => (addr, nonce),
+                _ => return Err(PoolError::PoolFull),
+            };
+            self.remove(&victim, nonce);
+        }
+
+        let nonce = txn.nonce;
+        let pooled = PooledTxn {
+            txn,
+            seq: self.next_seq,
+        };
+        self.next_seq += 1;
+        self.len += 1;
+        if nonce == self.next_pending_nonce(&sender, state_nonce) {
+            self.pending
+                .entry(sender)
+                .or_default()
+                .insert(nonce, pooled);
+            self.promote(&sender);
+        } else {
+            self.queued.entry(sender).or_default().insert(nonce, pooled);
+        }
+        Ok(())
+    }
+
+    pub fn get(&self, sender: &ETHAddress, nonce: u32) -> Option<&ETHTxn> {
+        self.pending
+            .get(sender)
+            .and_then(|txns| txns.get(&nonce))
+            .or_else(|| self.queued.get(sender).and_then(|txns| txns.get(&nonce)))
+            .map(|pooled| &pooled.txn)
+    }
+
+    fn get_mut(&mut self, sender: &ETHAddress, nonce: u32) -> Option<&mut PooledTxn> {
+        let pooled = match self.pending.get_mut(sender) {
+            Some(txns) => txns.get_mut(&nonce),
+            None => None,
+        };
+        match pooled {
+            Some(pooled) => Some(pooled),
+            None => self.queued.get_mut(sender)?.get_mut(&nonce),
+        }
+    }
+
+    /// Removes and returns up to `max` pending transactions for a block with
+    /// `base_fee`, the best paying to the miner first, without taking any
+    /// sender's nonces out of order. A transaction that can't pay the base
+    /// fee stays, along with the rest of its sender's.
+    pub fn take_best(&mut self, max: usize, base_fee: Wei) -> Vec<(ETHAddress, ETHTxn)> {
+        self.take_best_by(max, base_fee, |_, _| true)
+    }
+
+    /// Like `take_best`, but each transaction is first offered to `accept`.
+    /// One it turns down stays in the pool, and the rest of its sender's
+    /// aren't offered.
+    pub fn take_best_by<F>(
+        &mut self,
+        max: usize,
+        base_fee: Wei,
+        mut accept: F,
+    ) -> Vec<(ETHAddress, ETHTxn)>
+    where
+        F: FnMut(&ETHAddress, &ETHTxn) -> bool,
+    {
+        // each sender competes with their lowest pending nonce
+        let mut heads: BinaryHeap<Head> = self
+            .pending
+            .iter()
+            .filter_map(|(sender, txns)| Head::new(*sender, txns.values().next()?, base_fee))
+            .collect();
+        let mut taken = Vec::new();
+        while taken.len() < max {
+            let head = match heads.pop() {
+                Some(head) => head,
+                None => break,
+            };
+            let txns = self.pending.get_mut(&head.sender).unwrap();
+            if !accept(&head.sender, &txns.values().next().unwrap().txn) {
+                continue;
+            }
+            let (_, pooled) = txns.pop_first().unwrap();
+            match txns.values().next() {
+                Some(next) => heads.extend(Head::new(head.sender, next, base_fee)),
+                None => {
+                    self.pending.remove(&head.sender);
+                }
+            }
+            self.len -= 1;
+            taken.push((head.sender, pooled.txn));
+        }
+        taken
+    }
+
+    fn remove(&mut self, sender: &ETHAddress, nonce: u32) {
+        for map in [&mut self.pending, &mut self.queued] {
+            if let Some(txns) = map.get_mut(sender) {
+                if txns.remove(&nonce).is_some() {
+                    self.len -= 1;
+                }
+                if txns.is_empty() {
+                    map.remove(sender);
+                }
+            }
+        }
+    }
+
+    fn sender_len(&self, sender: &ETHAddress) -> usize {
+        self.pending.get(sender).map_or(0, BTreeMap::len)
+            + self.queued.get(sender).map_or(0, BTreeMap::len)
+    }
+
+    fn next_pending_nonce(&self, sender: &ETHAddress, state_nonce: u32) -> u32 {
+        match self
+            .pending
+            .get(sender)
+            .and_then(|txns| txns.keys().next_back())
+        {
+            Some(&last) => last + 1,
+            None => state_nonce + 1,
+        }
+    }
+
+    // moves queued transactions over once the gap before them is filled
+    fn promote(&mut self, sender: &ETHAddress) {
+        let pending = self.pending.get_mut(sender).unwrap();
+        if let Some(queued) = self.queued.get_mut(sender) {
+            let mut next = pending.keys().next_back().unwrap() + 1;
+            while let Some(pooled) = queued.remove(&next) {
+                pending.insert(next, pooled);
+                next += 1;
+            }
+            if queued.is_empty() {
+                self.queued.remove(sender);
+            }
+        }
+    }
+
+    // only a sender's highest nonce can go without leaving a gap, and never
+    // to make room for a later nonce of the same sender
+    fn cheapest_evictable(
+        &self,
+        for_sender: &ETHAddress,
+        for_nonce: u32,
+        base_fee: Wei,
+    ) -> Option<(ETHAddress, u32, (Option<Wei>, Wei))> {
+        let senders = self.pending.keys().chain(self.queued.keys());
+        senders
+            .filter_map(|sender| {
+                let txns = self
+                    .queued
+                    .get(sender)
+                    .or_else(|| self.pending.get(sender))?;
+                let (&nonce, pooled) = txns.iter().next_back()?;
+                if sender == for_sender && nonce < for_nonce {
+                    return None;
+                }
+                let rank = eviction_rank(&pooled.txn, base_fee);
+                Some((*sender, nonce, rank, pooled.seq))
+            })
+            // the newest of equally cheap transactions goes first
+            .min_by(|a, b| a.2.cmp(&b.2).then(b.3.cmp(&a.3)))
+            .map(|(sender, nonce, rank, _)| (sender, nonce, rank))
+    }
+}
+
+impl Default for TxPool {
+    fn default() -> TxPool {
+        TxPool::new(DEFAULT_POOL_SIZE, DEFAULT_SENDER_LIMIT)
+    }
+}
+
+// what `txn` pays the miner per unit of gas in a block with `base_fee`, or
+// None if it can't pay the base fee
+fn tip(txn: &ETHTxn, base_fee: Wei) -> Option<Wei> {
+    let gasprice = txn.effective_gasprice(base_fee)?;
+    Some(gasprice.saturating_sub(base_fee))
+}
+
+// transactions that can't pay the base fee rank lowest, those closest to
+// paying it highest among them
+fn eviction_rank(txn: &ETHTxn, base_fee: Wei) -> (Option<Wei>, Wei) {
+    (tip(txn, base_fee), txn.get_max_fee())
+}
+
+// a sender's next transaction, ordered by what it pays the miner per unit
+// of gas, then arrival
+struct Head {
+    sender: ETHAddress,
+    tip: Wei,
+    seq: u64,
+}
+
+impl Head {
+    // None if the transaction can't pay the base fee
+    fn new(sender: ETHAddress, pooled: &PooledTxn, base_fee: Wei) -> Option<Head> {
+        Some(Head {
+            sender,
+            tip: tip(&pooled.txn, base_fee)?,
+            seq: pooled.seq,
+        })
+    }
+}
+
+impl PartialEq for Head {
+    fn eq(&self, other: &Head) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Head {}
+
+impl PartialOrd for Head {
+    fn partial_cmp(&self, other: &Head) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Head {
+    // the max-heap pops the highest tip, then the earliest arrival
+    fn cmp(&self, other: &Head) -> Ordering {
+        self.tip.cmp(&other.tip).then(other.seq.cmp(&self.seq))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        super::aliases::ETHAddress, super::ethtxn::utils::get_bs_ecsda_field,
+        super::ethtxn::ETHTxn, super::ethtxn::DEFAULT_CHAIN_ID, super::rlp::HashEncoding,
+        super::wei::Wei, PoolError, TxPool,
+    };
+
+    const ALICE: ETHAddress = [1; 20];
+    const BOB: ETHAddress = [2; 20];
+    const CAROL: ETHAddress = [3; 20];
+
+    // the pool takes the sender as given, so the signature doesn't matter
+    fn txn(nonce: u32, gasprice: u128) -> ETHTxn {
+        let key = secp256k1::SecretKey::parse(&[9; 32]).unwrap();
+        ETHTxn {
+            nonce,
+            chain_id: DEFAULT_CHAIN_ID,
+            gasprice: Wei::from_wei(gasprice),
+            max_fee: None,
+            max_priority_fee: None,
+            gaslimit: 100,
+            recipient: Some([7; 20]),
+            value: Wei::from_wei(0),
+            code: vec![],
+            encoding: HashEncoding::Bincode,
+            ecdsa_fields: get_bs_ecsda_field(&key),
+        }
+    }
+
+    fn order(taken: &[(ETHAddress, ETHTxn)]) -> Vec<(u8, u32)> {
+        taken
+            .iter()
+            .map(|(sender, txn)| (sender[0], txn.nonce))
+            .collect()
+    }
+
+    #[test]
+    fn future_nonces_wait_for_the_gap() {
+        let mut pool = TxPool::default();
+        pool.add(txn(3, 1), ALICE, 0, Wei::from_wei(0)).unwrap();
+        pool.add(txn(2, 1), ALICE, 0, Wei::from_wei(0)).unwrap();
+        assert_eq!((pool.pending_len(), pool.queued_len()), (0, 2));
+        assert!(pool.take_best(10, Wei::from_wei(0)).is_empty());
+
+        pool.add(txn(1, 1), ALICE, 0, Wei::from_wei(0)).unwrap();
+        assert_eq!((pool.pending_len(), pool.queued_len()), (3, 0));
+        assert_eq!(
+            order(&pool.take_best(10, Wei::from_wei(0))),
+            vec![(1, 1), (1, 2), (1, 3)]
+        );
+        assert!(pool.is_empty());
+    }
+
+    #[test]
+    fn best_gasprice_first_within_nonce_order() {
+        let mut pool = TxPool::default();
+        pool.add(txn(1, 2), ALICE, 0, Wei::from_wei(0)).unwrap();
+        pool.add(txn(2, 9), ALICE, 0, Wei::from_wei(0)).unwrap();
+        pool.add(txn(5, 5), BOB, 4, Wei::from_wei(0)).unwrap();
+        pool.add(txn(6, 1), BOB, 4, Wei::from_wei(0)).unwrap();
+        // Alice's nonce 2 pays the most but has to wait for nonce 1
+        assert_eq!(
+            order(&pool.take_best(3, Wei::from_wei(0))),
+            vec![(2, 5), (1, 1), (1, 2)]
+        );
+        assert_eq!(order(&pool.take_best(3, Wei::from_wei(0))), vec![(2, 6)]);
+    }
+
+    #[test]
+    fn ordered_by_tip_over_the_base_fee() {
+        let mut pool = TxPool::default();
+        // pays 40 + 2 at a base fee of 40
+        let mut capped = txn(1, 0);
+        capped.max_fee = Some(Wei::from_wei(100));
+        capped.max_priority_fee = Some(Wei::from_wei(2));
+        pool.add(capped, ALICE, 0, Wei::from_wei(0)).unwrap();
+        // a legacy gasprice of 50 tips 10
+        pool.add(txn(1, 50), BOB, 0, Wei::from_wei(0)).unwrap();
+        // can't pay the base fee, so neither it nor Carol's next can go
+        pool.add(txn(1, 30), CAROL, 0, Wei::from_wei(0)).unwrap();
+        pool.add(txn(2, 90), CAROL, 0, Wei::from_wei(0)).unwrap();
+
+        assert_eq!(
+            order(&pool.take_best(10, Wei::from_wei(40))),
+            vec![(2, 1), (1, 1)]
+        );
+        assert_eq!(pool.pending_len(), 2);
+        assert_eq!(
+            order(&pool.take_best(10, Wei::from_wei(30))),
+            vec![(3, 1), (3, 2)]
+        );
+    }
+
+    #[test]
+    fn rejects_stale_and_known_nonces() {
+        let mut pool = TxPool::default();
+        assert_eq!(
+            pool.add(txn(3, 1), ALICE, 3, Wei::from_wei(0)),
+            Err(PoolError::StaleNonce)
+        );
+        pool.add(txn(4, 1), ALICE, 3, Wei::from_wei(0)).unwrap();
+        assert_eq!(
+            pool.add(txn(4, 1), ALICE, 3, Wei::from_wei(0)),
+            Err(PoolError::AlreadyKnown)
+        );
+        pool.add(txn(6, 1), ALICE, 3, Wei::from_wei(0)).unwrap();
+        let mut different = txn(6, 1);
+        different.value = Wei::from_wei(1);
+        assert_eq!(
+            pool.add(different, ALICE, 3, Wei::from_wei(0)),
+            Err(PoolError::ReplacementUnderpriced)
+        );
+        assert_eq!(pool.len(), 2);
+    }
+
+    #[test]
+    fn replace_by_fee() {
+        let mut pool = TxPool::new(2, 2);
+        pool.add(txn(1, 100), ALICE, 0, Wei::from_wei(0)).unwrap();
+        pool.add(txn(3, 100), ALICE, 0, Wei::from_wei(0)).unwrap();
+
+        // 10% more is needed by default
+        assert_eq!(
+            pool.add(txn(1, 109), ALICE, 0, Wei::from_wei(0)),
+            Err(PoolError::ReplacementUnderpriced)
+        );
+        pool.add(txn(1, 110), ALICE, 0, Wei::from_wei(0)).unwrap();
+        // a full pool and sender limit don't stop replacements
+        pool.add(txn(3, 200), ALICE, 0, Wei::from_wei(0)).unwrap();
+        assert_eq!(pool.len(), 2);
+        assert_eq!(pool.get(&ALICE, 1).unwrap().gasprice, Wei::from_wei(110));
+        assert_eq!(pool.get(&ALICE, 3).unwrap().gasprice, Wei::from_wei(200));
+        assert_eq!((pool.pending_len(), pool.queued_len()), (1, 1));
+
+        pool.set_price_bump(50);
+        assert_eq!(
+            pool.add(txn(1, 164), ALICE, 0, Wei::from_wei(0)),
+            Err(PoolError::ReplacementUnderpriced)
+        );
+        pool.add(txn(1, 165), ALICE, 0, Wei::from_wei(0)).unwrap();
+        let taken = pool.take_best(10, Wei::from_wei(0));
+        assert_eq!(taken.len(), 1);
+        assert_eq!(taken[0].1.gasprice, Wei::from_wei(165));
+
+        // any bump on a zero fee is zero, but the replacement must pay more
+        let mut pool = TxPool::new(2, 2);
+        pool.add(txn(1, 0), ALICE, 0, Wei::from_wei(0)).unwrap();
+        let mut free = txn(1, 0);
+        free.value = Wei::from_wei(1);
+        assert_eq!(
+            pool.add(free, ALICE, 0, Wei::from_wei(0)),
+            Err(PoolError::ReplacementUnderpriced)
+        );
+        pool.add(txn(1, 1), ALICE, 0, Wei::from_wei(0)).unwrap();
+        assert_eq!(pool.get(&ALICE, 1).unwrap().gasprice, Wei::from_wei(1));
+    }
+
+    #[test]
+    fn size_limits_and_eviction() {
+        let mut pool = TxPool::new(3, 2);
+        pool.add(txn(1, 5), ALICE, 0, Wei::from_wei(0)).unwrap();
+        pool.add(txn(2, 5), ALICE, 0, Wei::from_wei(0)).unwrap();
+        assert_eq!(
+            pool.add(txn(3, 9), ALICE, 0, Wei::from_wei(0)),
+            Err(PoolError::SenderFull)
+        );
+
+        pool.add(txn(1, 3), BOB, 0, Wei::from_wei(0)).unwrap();
+        // full, and nothing is cheaper than a gasprice of 3
+        assert_eq!(
+            pool.add(txn(1, 3), CAROL, 0, Wei::from_wei(0)),
+            Err(PoolError::PoolFull)
+        );
+        // Bob's nonce 1 is the cheapest, but his nonce 2 needs it
+        pool.add(txn(2, 9), BOB, 0, Wei::from_wei(0)).unwrap();
+        assert_eq!(pool.len(), 3);
+        assert!(pool.get(&ALICE, 2).is_none());
+        // only the last of Bob's nonces can go, and it pays more
+        assert_eq!(
+            pool.add(txn(1, 4), CAROL, 0, Wei::from_wei(0)),
+            Err(PoolError::PoolFull)
+        );
+
+        assert_eq!(
+            order(&pool.take_best(10, Wei::from_wei(0))),
+            vec![(1, 1), (2, 1), (2, 2)]
+        );
+        assert!(pool.is_empty());
+    }
+
+    #[test]
+    fn eviction_follows_the_tip() {
+        let base_fee = Wei::from_wei(40);
+        let mut pool = TxPool::new(2, 2);
+        // the highest max fee, but it only tips 1
+        let mut capped = txn(1, 0);
+        capped.max_fee = Some(Wei::from_wei(100));
+        capped.max_priority_fee = Some(Wei::from_wei(1));
+        pool.add(capped, ALICE, 0, base_fee).unwrap();
+        pool.add(txn(1, 50), BOB, 0, base_fee).unwrap();
+
+        // tips 5, so Alice's goes rather than Bob's tip of 10
+        pool.add(txn(1, 45), CAROL, 0, base_fee).unwrap();
+        assert!(pool.get(&ALICE, 1).is_none());
+        assert_eq!(
+            pool.add(txn(2, 44), CAROL, 0, base_fee),
+            Err(PoolError::PoolFull)
+        );
+
+        // nothing that can't pay the base fee stays for one that can
+        let mut pool = TxPool::new(2, 2);
+        pool.add(txn(1, 39), ALICE, 0, base_fee).unwrap();
+        pool.add(txn(1, 41), BOB, 0, base_fee).unwrap();
+        pool.add(txn(1, 40), CAROL, 0, base_fee).unwrap();
+        assert!(pool.get(&ALICE, 1).is_none());
+        assert_eq!(order(&pool.take_best(10, base_fee)), vec![(2, 1), (3, 1)]);
+    }
+}
@@ -1,8 +1,21 @@
 use super::gas::Gas;
 use std::clone::Clone;
-use std::ops::{Add, AddAssign, Sub};
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Div, Mul, Sub};
+use std::str::FromStr;
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Copy)]
+// (name, decimal places) for every unit amounts can be written in
+const UNITS: &[(&str, u32)] = &[
+    ("wei", 0),
+    ("gwei", 9),
+    ("szabo", 12),
+    ("finney", 15),
+    ("ether", 18),
+    ("eth", 18),
+];
+
+#[derive(Debug, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Copy)]
 pub struct Wei {
     wei: u128,
 }
@@ -20,39 +33,54 @@ impl Wei {
         Self { wei: init }
     }
     pub fn from_szabo(init: u128) -> Self {
-        Self {
-            wei: init * Self::WEI_PER_SZABO,
-        }
+        Self::from_units(init, Self::WEI_PER_SZABO)
     }
     pub fn from_finney(init: u128) -> Self {
-        Self {
-            wei: init * Self::WEI_PER_FINNEY,
-        }
+        Self::from_units(init, Self::WEI_PER_FINNEY)
     }
     pub fn from_eth(init: u128) -> Self {
-        Self {
-            wei: init * Self::WEI_PER_ETH,
-        }
+        Self::from_units(init, Self::WEI_PER_ETH)
     }
     pub fn from_gwei(init: u128) -> Self {
-        Self {
-            wei: init * Self::WEI_PER_GWEI,
-        }
+        Self::from_units(init, Self::WEI_PER_GWEI)
     }
+    // panics if the fee doesn't fit; `gasprice * gas` is the checked form
     pub fn from_gas(gasprice: Wei, gas: Gas) -> Self {
-        Self {
-            wei: gasprice.wei * gas,
-        }
+        (gasprice * gas).expect("fee overflows Wei")
+    }
+
+    fn from_units(init: u128, wei_per_unit: u128) -> Self {
+        Wei::from_wei(init)
+            .checked_mul(wei_per_unit)
+            .expect("amount overflows Wei")
+    }
+
+    pub fn checked_add(self, other: Wei) -> Option<Wei> {
+        self.wei.checked_add(other.wei).map(Wei::from_wei)
+    }
+    pub fn checked_sub(self, other: Wei) -> Option<Wei> {
+        self.wei.checked_sub(other.wei).map(Wei::from_wei)
+    }
+    pub fn checked_mul(self, factor: u128) -> Option<Wei> {
+        self.wei.checked_mul(factor).map(Wei::from_wei)
+    }
+    pub fn saturating_add(self, other: Wei) -> Wei {
+        Wei::from_wei(self.wei.saturating_add(other.wei))
+    }
+    pub fn saturating_sub(self, other: Wei) -> Wei {
+        Wei::from_wei(self.wei.saturating_sub(other.wei))
+    }
+    pub fn saturating_mul(self, factor: u128) -> Wei {
+        Wei::from_wei(self.wei.saturating_mul(factor))
     }
 }
 
+// adding past u128::MAX wei is a bug, so it panics even in release builds
 impl Add for Wei {
     type Output = Wei;
 
     fn add(self, other: Wei) -> Self::Output {
-        Self {
-            wei: self.wei + other.wei,
-        }
+        self.checked_add(other).expect("Wei overflow")
     }
 }
 
@@ -60,13 +88,43 @@ impl Sub for Wei {
     type Output = Option<Wei>;
 
     fn sub(self, other: Wei) -> Self::Output {
-        self.wei.checked_sub(other.wei).map(|c| Self { wei: c })
+        self.checked_sub(other)
+    }
+}
+
+// the fee for `gas` at a price of `self` per unit, or None if it overflows
+impl Mul<Gas> for Wei {
+    type Output = Option<Wei>;
+
+    fn mul(self, gas: Gas) -> Self::Output {
+        self.checked_mul(gas)
+    }
+}
+
+// how many times `other` fits in `self`, or None if `other` is zero
+impl Div for Wei {
+    type Output = Option<u128>;
+
+    fn div(self, other: Wei) -> Self::Output {
+        self.wei.checked_div(other.wei)
     }
 }
 
 impl AddAssign for Wei {
     fn add_assign(&mut self, other: Wei) {
-        self.wei += other.wei;
+        *self = *self + other;
+    }
+}
+
+impl Sum for Wei {
+    fn sum<I: Iterator<Item = Wei>>(iter: I) -> Wei {
+        iter.fold(Wei::from_wei(0), Add::add)
+    }
+}
+
+impl<'a> Sum<&'a Wei> for Wei {
+    fn sum<I: Iterator<Item = &'a Wei>>(iter: I) -> Wei {
+        iter.cloned().sum()
     }
 }
 
@@ -76,6 +134,110 @@ impl Clone for Wei {
     }
 }
 
+/// Writes the amount in ether, gwei or wei, whichever is the largest unit
+/// it has at least one of: "1.5 ether", "20 gwei", "7 wei". A precision
+/// rounds to that many decimal places, so `{:.2}` gives "1.50 ether".
+impl fmt::Display for Wei {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (name, decimals) = if self.wei >= Self::WEI_PER_ETH {
+            ("ether", 18)
+        } else if self.wei >= Self::WEI_PER_GWEI {
+            ("gwei", 9)
+        } else {
+            ("wei", 0)
+        };
+        let unit = 10u128.pow(decimals);
+        let mut whole = self.wei / unit;
+        let mut frac = self.wei % unit;
+        let mut places = decimals as usize;
+        // digits shown after the point; any beyond `places` are zeros
+        let width;
+        match f.precision() {
+            Some(precision) if precision < places => {
+                // round half up to the requested places
+                let scale = 10u128.pow((places - precision) as u32);
+                frac = (frac + scale / 2) / scale;
+                if frac == 10u128.pow(precision as u32) {
+                    whole += 1;
+                    frac = 0;
+                }
+                places = precision;
+                width = precision;
+            }
+            Some(precision) => width = precision,
+            None => {
+                while places > 0 && frac.is_multiple_of(10) {
+                    frac /= 10;
+                    places -= 1;
+                }
+                width = places;
+            }
+        }
+        if width == 0 {
+            write!(f, "{} {}", whole, name)
+        } else {
+            let digits = if places == 0 {
+                String::new()
+            } else {
+                format!("{:0places$}", frac, places = places)
+            };
+            write!(f, "{}.{:0<width$} {}", whole, digits, name, width = width)
+        }
+    }
+}
+
+/// Parses an amount with an optional unit, which defaults to wei: "1000",
+/// "20 gwei", "1.5ether". Fractions are exact, so they can't go below a wei.
+impl FromStr for Wei {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Wei, String> {
+        let text = text.trim();
+        let split = text
+            .find(|c: char| c.is_ascii_alphabetic())
+            .unwrap_or(text.len());
+        let (number, unit) = (text[..split].trim_end(), &text[split..]);
+        let decimals = if unit.is_empty() {
+            0
+        } else {
+            match UNITS
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(unit))
+            {
+                Some(&(_, decimals)) => decimals,
+                None => return Err(format!("unknown unit '{}'", unit)),
+            }
+        };
+
+        let (whole, frac) = match number.find('.') {
+            Some(pos) => (&number[..pos], &number[pos + 1..]),
+            None => (number, ""),
+        };
+        let is_digits = |part: &str| part.chars().all(|c| c.is_ascii_digit());
+        if whole.len() + frac.len() == 0 || !is_digits(whole) || !is_digits(frac) {
+            return Err(format!("'{}' isn't an amount", number));
+        }
+        let frac = frac.trim_end_matches('0');
+        if frac.len() > decimals as usize {
+            return Err(format!("'{}' is a fraction of a wei", text));
+        }
+
+        let too_large = || format!("'{}' is too large", text);
+        let whole = match whole {
+            "" => 0,
+            whole => whole.parse::<u128>().map_err(|_| too_large())?,
+        };
+        let frac = match frac {
+            "" => 0,
+            frac => frac.parse::<u128>().unwrap() * 10u128.pow(decimals - frac.len() as u32),
+        };
+        Wei::from_wei(whole)
+            .checked_mul(10u128.pow(decimals))
+            .and_then(|wei| wei.checked_add(Wei::from_wei(frac)))
+            .ok_or_else(too_large)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::Wei;
@@ -97,5 +259,93 @@ mod tests {
             Wei::from_wei(56) - Wei::from_wei(51),
             Some(Wei::from_wei(5))
         );
+        assert_eq!(Wei::from_gwei(3) * 7, Some(Wei::from_gwei(21)));
+        assert_eq!(Wei::from_wei(u128::MAX / 2) * 3, None);
+        assert_eq!(Wei::from_wei(17) / Wei::from_wei(5), Some(3));
+        assert_eq!(Wei::from_wei(17) / Wei::from_wei(0), None);
+        let amounts = [Wei::from_wei(1), Wei::from_wei(2), Wei::from_wei(3)];
+        assert_eq!(amounts.iter().sum::<Wei>(), Wei::from_wei(6));
+    }
+    #[test]
+    fn test_wei_overflow() {
+        let max = Wei::from_wei(u128::MAX);
+        assert_eq!(max.checked_add(Wei::from_wei(1)), None);
+        assert_eq!(max.saturating_add(Wei::from_wei(1)), max);
+        assert_eq!(max.checked_mul(2), None);
+        assert_eq!(max.saturating_mul(2), max);
+        assert_eq!(
+            Wei::from_wei(3).saturating_sub(Wei::from_wei(5)),
+            Wei::from_wei(0)
+        );
+        assert_eq!(Wei::from_wei(3).checked_sub(Wei::from_wei(5)), None);
+    }
+    #[test]
+    fn test_wei_display() {
+        assert_eq!(Wei::from_finney(1500).to_string(), "1.5 ether");
+        assert_eq!(Wei::from_gwei(20).to_string(), "20 gwei");
+        assert_eq!(Wei::from_wei(7).to_string(), "7 wei");
+        assert_eq!(Wei::from_wei(0).to_string(), "0 wei");
+        assert_eq!(Wei::from_wei(1_000_000_001).to_string(), "1.000000001 gwei");
+        assert_eq!(format!("{:.2}", Wei::from_finney(1500)), "1.50 ether");
+        assert_eq!(
+            format!("{:.3}", Wei::from_wei(1_234_567_000_000_000_000)),
+            "1.235 ether"
+        );
+        assert_eq!(format!("{:.1}", Wei::from_finney(1999)), "2.0 ether");
+        assert_eq!(format!("{:.0}", Wei::from_finney(2500)), "3 ether");
+        assert_eq!(format!("{:.2}", Wei::from_wei(7)), "7.00 wei");
+        // more places than a u128 can scale to
+        assert_eq!(
+            format!("{:.40}", Wei::from_wei(7)),
+            format!("7.{} wei", "0".repeat(40))
+        );
+        assert_eq!(
+            format!("{:.39}", Wei::from_finney(1500)),
+            format!("1.5{} ether", "0".repeat(38))
+        );
+    }
+    #[test]
+    fn test_wei_parse() {
+        assert_eq!("1000".parse(), Ok(Wei::from_wei(1000)));
+        assert_eq!("20 gwei".parse(), Ok(Wei::from_gwei(20)));
+        assert_eq!("1.5ether".parse(), Ok(Wei::from_finney(1500)));
+        assert_eq!(" 0.25 ETH ".parse(), Ok(Wei::from_finney(250)));
+        assert_eq!(".5 szabo".parse(), Ok(Wei::from_gwei(500)));
+        assert_eq!("3. finney".parse(), Ok(Wei::from_finney(3)));
+        assert_eq!("0.000000000000000001 ether".parse(), Ok(Wei::from_wei(1)));
+        assert_eq!(
+            "2.50 wei".parse::<Wei>(),
+            Err("'2.50 wei' is a fraction of a wei".to_string())
+        );
+        assert!("1.5 wei".parse::<Wei>().is_err());
+        assert!("0.0000000000000000001 ether".parse::<Wei>().is_err());
+        assert!("5 dollars".parse::<Wei>().is_err());
+        assert!("ether".parse::<Wei>().is_err());
+        assert!(". ether".parse::<Wei>().is_err());
+        assert!("-1 ether".parse::<Wei>().is_err());
+        assert!("1e18".parse::<Wei>().is_err());
+        assert!("400000000000000000000 ether".parse::<Wei>().is_err());
+
+        for &wei in &[
+            0,
+            1,
+            999,
+            1_000_000_000,
+            123_456_789_012_345_678_901,
+            u128::MAX,
+        ] {
+            let amount = Wei::from_wei(wei);
+            assert_eq!(amount.to_string().parse(), Ok(amount));
+        }
+    }
+    #[test]
+    #[should_panic(expected = "Wei overflow")]
+    fn test_wei_add_overflow_panics() {
+        let _ = Wei::from_wei(u128::MAX) + Wei::from_wei(1);
+    }
+    #[test]
+    #[should_panic(expected = "amount overflows Wei")]
+    fn test_wei_from_eth_overflow_panics() {
+        Wei::from_eth(u128::MAX);
     }
 }
@@ -0,0 +1,164 @@
+use std::collections::HashMap;
+
+use eth::asm::AsmItem;
+use eth::evmexec::MEMORY_SIZE;
+use eth::ops::Op;
+
+use super::parser::{BinOp, Expr, Stmt, ValueOp};
+use super::CompileError;
+
+/// Lowers a program to assembly items. Every variable gets its own memory
+/// slot, so a program can declare at most `MEMORY_SIZE` of them.
+pub fn generate(program: &[Stmt]) -> Result<Vec<AsmItem>, CompileError> {
+    let mut gen = Generator {
+        items: vec![],
+        slots: HashMap::new(),
+        labels: 0,
+    };
+    gen.block(program)?;
+    gen.op(Op::STOP);
+    Ok(gen.items)
+}
+
+struct Generator {
+    items: Vec<AsmItem>,
+    slots: HashMap<String, u8>,
+    labels: usize,
+}
+
+impl Generator {
+    fn op(&mut self, op: Op) {
+        self.items.push(AsmItem::Op(op));
+    }
+
+    fn new_label(&mut self) -> String {
+        // user code can't name labels, so these never collide
+        self.labels += 1;
+        format!("_L{}", self.labels)
+    }
+
+    fn jump(&mut self, op: Op, label: &str) {
+        self.items.push(AsmItem::PushLabel(label.to_string()));
+        self.op(op);
+    }
+
+    fn slot(&self, name: &str, line: usize) -> Result<u8, CompileError> {
+        match self.slots.get(name) {
+            Some(slot) => Ok(*slot),
+            None => Err(CompileError::new(
+                line,
+                format!("use of undeclared variable '{}'", name),
+            )),
+        }
+    }
+
+    fn block(&mut self, stmts: &[Stmt]) -> Result<(), CompileError> {
+        for stmt in stmts {
+            self.statement(stmt)?;
+        }
+        Ok(())
+    }
+
+    fn statement(&mut self, stmt: &Stmt) -> Result<(), CompileError> {
+        match stmt {
+            Stmt::Let(name, expr, line) => {
+                if self.slots.contains_key(name) {
+                    return Err(CompileError::new(
+                        *line,
+                        format!("variable '{}' is already declared", name),
+                    ));
+                }
+                if self.slots.len() >= MEMORY_SIZE {
+                    return Err(CompileError::new(*line, "too many variables".to_string()));
+                }
+                // evaluated before the variable is in scope
+                self.expr(expr)?;
+                let slot = self.slots.len() as u8;
+                self.slots.insert(name.clone(), slot);
+                self.op(Op::PUSH1(slot));
+                self.op(Op::MSTORE);
+            }
+            Stmt::Assign(name, expr, line) => {
+                let slot = self.slot(name, *line)?;
+                self.expr(expr)?;
+                self.op(Op::PUSH1(slot));
+                self.op(Op::MSTORE);
+            }
+            Stmt::Value(op, expr) => {
+                self.expr(expr)?;
+                self.op(match op {
+                    ValueOp::Set => Op::SETVAL,
+                    ValueOp::Add => Op::ADDVAL,
+                    ValueOp::Sub => Op::SUBVAL,
+                });
+            }
+            Stmt::If(cond, then_block, else_block) => {
+                let else_label = self.new_label();
+                let end_label = self.new_label();
+                self.expr(cond)?;
+                self.op(Op::ISZERO);
+                self.jump(Op::JUMPI, &else_label);
+                self.block(then_block)?;
+                self.jump(Op::JUMP, &end_label);
+                self.items.push(AsmItem::Label(else_label));
+                self.block(else_block)?;
+                self.items.push(AsmItem::Label(end_label));
+            }
+            Stmt::While(cond, body) => {
+                let start_label = self.new_label();
+                let end_label = self.new_label();
+                self.items.push(AsmItem::Label(start_label.clone()));
+                self.expr(cond)?;
+                self.op(Op::ISZERO);
+                self.jump(Op::JUMPI, &end_label);
+                self.block(body)?;
+                self.jump(Op::JUMP, &start_label);
+                self.items.push(AsmItem::Label(end_label));
+            }
+            Stmt::Stop => self.op(Op::STOP),
+        }
+        Ok(())
+    }
+
+    fn expr(&mut self, expr: &Expr) -> Result<(), CompileError> {
+        match expr {
+            Expr::Num(num) => self.op(Op::PUSH1(*num)),
+            Expr::Var(name, line) => {
+                let slot = self.slot(name, *line)?;
+                self.op(Op::PUSH1(slot));
+                self.op(Op::MLOAD);
+            }
+            Expr::Not(inner) => {
+                self.expr(inner)?;
+                self.op(Op::ISZERO);
+            }
+            Expr::Binary(op, lhs, rhs) => {
+                // binary ops take their left operand from the top of the stack
+                self.expr(rhs)?;
+                self.expr(lhs)?;
+                match op {
+                    BinOp::Add => self.op(Op::ADD),
+                    BinOp::Sub => self.op(Op::SUB),
+                    BinOp::Mul => self.op(Op::MUL),
+                    BinOp::Div => self.op(Op::DIV),
+                    BinOp::Lt => self.op(Op::LT),
+                    BinOp::Gt => self.op(Op::GT),
+                    BinOp::Eq => self.op(Op::EQ),
+                    BinOp::Le => {
+                        self.op(Op::GT);
+                        self.op(Op::ISZERO);
+                    }
+                    BinOp::Ge => {
+                        self.op(Op::LT);
+                        self.op(Op::ISZERO);
+                    }
+                    BinOp::NotEq => {
+                        self.op(Op::EQ);
+                        self.op(Op::ISZERO);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,167 @@
+use super::CompileError;
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Token {
+    Num(u8),
+    Ident(String),
+
+    Let,
+    If,
+    Else,
+    While,
+    Value,
+    Stop,
+
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    Lt,
+    Gt,
+    Le,
+    Ge,
+    EqEq,
+    NotEq,
+    Not,
+    Assign,
+    PlusAssign,
+    MinusAssign,
+
+    LParen,
+    RParen,
+    LBrace,
+    RBrace,
+    Semi,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Spanned {
+    pub token: Token,
+    pub line: usize,
+}
+
+pub fn tokenize(source: &str) -> Result<Vec<Spanned>, CompileError> {
+    let mut tokens = vec![];
+    let mut chars = source.chars().peekable();
+    let mut line = 1;
+
+    while let Some(&c) = chars.peek() {
+        if c == '\n' {
+            line += 1;
+            chars.next();
+            continue;
+        }
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+        if c == '#' {
+            // comment to end of line
+            while chars.peek().is_some_and(|&c| c != '\n') {
+                chars.next();
+            }
+            continue;
+        }
+
+        let token = if c.is_ascii_digit() {
+            let mut digits = String::new();
+            while let Some(&d) = chars.peek() {
+                if !d.is_ascii_digit() {
+                    break;
+                }
+                digits.push(d);
+                chars.next();
+            }
+            match digits.parse::<u8>() {
+                Ok(num) => Token::Num(num),
+                Err(_) => {
+                    return Err(CompileError::new(
+                        line,
+                        format!("{} doesn't fit in a byte", digits),
+                    ))
+                }
+            }
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let mut word = String::new();
+            while let Some(&d) = chars.peek() {
+                if !(d.is_ascii_alphanumeric() || d == '_') {
+                    break;
+                }
+                word.push(d);
+                chars.next();
+            }
+            match word.as_str() {
+                "let" => Token::Let,
+                "if" => Token::If,
+                "else" => Token::Else,
+                "while" => Token::While,
+                "value" => Token::Value,
+                "stop" => Token::Stop,
+                _ => Token::Ident(word),
+            }
+        } else {
+            chars.next();
+            let followed_by_eq = chars.peek() == Some(&'=');
+            let (token, two_chars) = match c {
+                '+' if followed_by_eq => (Token::PlusAssign, true),
+                '-' if followed_by_eq => (Token::MinusAssign, true),
+                '<' if followed_by_eq => (Token::Le, true),
+                '>' if followed_by_eq => (Token::Ge, true),
+                '=' if followed_by_eq => (Token::EqEq, true),
+                '!' if followed_by_eq => (Token::NotEq, true),
+                '+' => (Token::Plus, false),
+                '-' => (Token::Minus, false),
+                '*' => (Token::Star, false),
+                '/' => (Token::Slash, false),
+                '<' => (Token::Lt, false),
+                '>' => (Token::Gt, false),
+                '=' => (Token::Assign, false),
+                '!' => (Token::Not, false),
+                '(' => (Token::LParen, false),
+                ')' => (Token::RParen, false),
+                '{' => (Token::LBrace, false),
+                '}' => (Token::RBrace, false),
+                ';' => (Token::Semi, false),
+                c => return Err(CompileError::new(line, format!("unexpected '{}'", c))),
+            };
+            if two_chars {
+                chars.next();
+            }
+            token
+        };
+        tokens.push(Spanned { token, line });
+    }
+    Ok(tokens)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{tokenize, Token};
+
+    #[test]
+    fn test_tokens() {
+        let tokens: Vec<Token> = tokenize("let x = 12; # comment\nvalue += x <= 3;")
+            .unwrap()
+            .into_iter()
+            .map(|spanned| spanned.token)
+            .collect();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Let,
+                Token::Ident("x".to_string()),
+                Token::Assign,
+                Token::Num(12),
+                Token::Semi,
+                Token::Value,
+                Token::PlusAssign,
+                Token::Ident("x".to_string()),
+                Token::Le,
+                Token::Num(3),
+                Token::Semi,
+            ]
+        );
+        assert_eq!(tokenize("\n\n300").unwrap_err().line, Some(3));
+        assert!(tokenize("x @ y").is_err());
+    }
+}
@@ -0,0 +1,185 @@
+//! A tiny statement language that compiles to GadgetCoin bytecode.
+//!
+//! ```text
+//! let i = 0;
+//! while i < 5 {
+//!     i = i + 1;
+//!     value += i;
+//! }
+//! ```
+//!
+//! All values are bytes. `+` and `*` wrap around, `-` saturates at zero and
+//! dividing by zero gives zero, matching ADD, MUL, SUB and DIV. Comparisons
+//! evaluate to 0 or 1, and `if` and `while` treat any nonzero condition as
+//! true. `value` is write-only and maps onto SETVAL, ADDVAL and SUBVAL.
+
+use std::fmt;
+
+use eth::asm;
+use eth::ops::Op;
+
+pub mod codegen;
+pub mod lexer;
+pub mod parser;
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct CompileError {
+    // None when the program as a whole is at fault, e.g. it's too long
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl CompileError {
+    pub fn new(line: usize, message: String) -> CompileError {
+        CompileError {
+            line: Some(line),
+            message,
+        }
+    }
+}
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "line {}: {}", line, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+pub fn compile(source: &str) -> Result<Vec<u8>, CompileError> {
+    let tokens = lexer::tokenize(source)?;
+    let program = parser::parse(&tokens)?;
+    let items = codegen::generate(&program)?;
+    asm::assemble_items(&items).map_err(|err| CompileError {
+        line: None,
+        message: err.message,
+    })
+}
+
+pub fn compile_ops(source: &str) -> Result<Vec<Op>, CompileError> {
+    compile(source).map(|bytes| Op::from_bytes(&bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        super::eth::analyzer::analyze, super::eth::evmexec::ExecutionContext,
+        super::eth::gasschedule::GasSchedule, super::eth::wei::Wei, compile_ops,
+    };
+
+    // (program, initial value, expected final value)
+    const GOLDEN: &[(&str, u128, u128)] = &[
+        ("", 7, 7),
+        ("value = 6;", 100, 6),
+        ("value += 2;", 76, 78),
+        ("value -= 200;", 10, 0),
+        ("value = (1 + 2) * 3 - 4 / 2;", 0, 7),
+        ("value = 10 - 20;", 0, 0),
+        ("value = 5 / 0;", 0, 0),
+        (
+            "let x = 7;
+             if x > 5 { value += 10; } else { value -= 1; }",
+            1,
+            11,
+        ),
+        (
+            "let x = 3;
+             if x > 5 { value += 10; } else { value -= 1; }",
+            1,
+            0,
+        ),
+        (
+            "let x = 2;
+             if x == 1 { value = 10; }
+             else if x == 2 { value = 20; }
+             else { value = 30; }",
+            0,
+            20,
+        ),
+        (
+            "let i = 0;
+             let total = 0;
+             while i < 5 {
+                 i = i + 1;
+                 total = total + i;
+             }
+             value = total;",
+            0,
+            15,
+        ),
+        (
+            "# factorial
+             let n = 5;
+             let acc = 1;
+             while n > 1 {
+                 acc = acc * n;
+                 n = n - 1;
+             }
+             value = acc;",
+            0,
+            120,
+        ),
+        (
+            "# gcd by repeated subtraction
+             let a = 48;
+             let b = 18;
+             while a != b {
+                 if a > b { a = a - b; } else { b = b - a; }
+             }
+             value = a;",
+            0,
+            6,
+        ),
+        (
+            "let x = 4;
+             value = (x <= 4) + (x >= 5) + !(x == 3) + !x;",
+            0,
+            2,
+        ),
+        (
+            "value = 1;
+             stop;
+             value = 2;",
+            0,
+            1,
+        ),
+    ];
+
+    #[test]
+    fn golden_programs() {
+        for (source, initial, expected) in GOLDEN {
+            let ops = compile_ops(source).unwrap();
+            let analysis = analyze(&ops, &GasSchedule::default());
+            assert!(analysis.can_succeed, "analyzer rejected: {}", source);
+            assert!(analysis.stack_underflows.is_empty());
+            let mut engine = ExecutionContext::new(100_000, ops, Wei::from_wei(*initial));
+            assert!(engine.finish_executing(), "program failed: {}", source);
+            assert_eq!(
+                engine.get_value(),
+                Wei::from_wei(*expected),
+                "wrong value for: {}",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn semantic_errors() {
+        let err = compile_ops("let x = 1;\nvalue = y;").unwrap_err();
+        assert_eq!(err.line, Some(2));
+        assert!(err.message.contains("undeclared"));
+        assert!(compile_ops("let x = 1; let x = 2;").is_err());
+        assert!(compile_ops("let x = x;").is_err());
+        assert!(compile_ops("y = 1;").is_err());
+    }
+
+    #[test]
+    fn program_too_long() {
+        // the loop's end label lands past op 255
+        let body = "value += 1;".repeat(130);
+        let source = format!("while 1 {{ {} }}", body);
+        let err = compile_ops(&source).unwrap_err();
+        assert_eq!(err.line, None);
+    }
+}
@@ -0,0 +1,274 @@
+use super::lexer::{Spanned, Token};
+use super::CompileError;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Lt,
+    Gt,
+    Le,
+    Ge,
+    Eq,
+    NotEq,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Expr {
+    Num(u8),
+    Var(String, usize),
+    Binary(BinOp, Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ValueOp {
+    Set,
+    Add,
+    Sub,
+}
+
+// statements carry their line for error reporting
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Stmt {
+    Let(String, Expr, usize),
+    Assign(String, Expr, usize),
+    Value(ValueOp, Expr),
+    If(Expr, Vec<Stmt>, Vec<Stmt>),
+    While(Expr, Vec<Stmt>),
+    Stop,
+}
+
+pub fn parse(tokens: &[Spanned]) -> Result<Vec<Stmt>, CompileError> {
+    let mut parser = Parser { tokens, pos: 0 };
+    let mut program = vec![];
+    while parser.peek().is_some() {
+        program.push(parser.statement()?);
+    }
+    Ok(program)
+}
+
+struct Parser<'a> {
+    tokens: &'a [Spanned],
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|spanned| &spanned.token)
+    }
+
+    fn line(&self) -> usize {
+        match self.tokens.get(self.pos).or_else(|| self.tokens.last()) {
+            Some(spanned) => spanned.line,
+            None => 1,
+        }
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.peek().cloned();
+        if token.is_some() {
+            self.pos += 1;
+        }
+        token
+    }
+
+    fn error<T>(&self, message: String) -> Result<T, CompileError> {
+        Err(CompileError::new(self.line(), message))
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), CompileError> {
+        match self.peek() {
+            Some(token) if *token == expected => {
+                self.pos += 1;
+                Ok(())
+            }
+            Some(token) => self.error(format!("expected {:?}, found {:?}", expected, token)),
+            None => self.error(format!("expected {:?}, found end of input", expected)),
+        }
+    }
+
+    fn ident(&mut self) -> Result<String, CompileError> {
+        match self.next() {
+            Some(Token::Ident(name)) => Ok(name),
+            Some(token) => {
+                self.pos -= 1;
+                self.error(format!("expected a variable name, found {:?}", token))
+            }
+            None => self.error("expected a variable name, found end of input".to_string()),
+        }
+    }
+
+    fn statement(&mut self) -> Result<Stmt, CompileError> {
+        let line = self.line();
+        match self.next() {
+            Some(Token::Let) => {
+                let name = self.ident()?;
+                self.expect(Token::Assign)?;
+                let expr = self.expr()?;
+                self.expect(Token::Semi)?;
+                Ok(Stmt::Let(name, expr, line))
+            }
+            Some(Token::Ident(name)) => {
+                self.expect(Token::Assign)?;
+                let expr = self.expr()?;
+                self.expect(Token::Semi)?;
+                Ok(Stmt::Assign(name, expr, line))
+            }
+            Some(Token::Value) => {
+                let op = match self.next() {
+                    Some(Token::Assign) => ValueOp::Set,
+                    Some(Token::PlusAssign) => ValueOp::Add,
+                    Some(Token::MinusAssign) => ValueOp::Sub,
+                    _ => return self.error("expected =, += or -= after value".to_string()),
+                };
+                let expr = self.expr()?;
+                self.expect(Token::Semi)?;
+                Ok(Stmt::Value(op, expr))
+            }
+            Some(Token::If) => {
+                let cond = self.expr()?;
+                let then_block = self.block()?;
+                let else_block = if self.peek() == Some(&Token::Else) {
+                    self.pos += 1;
+                    if self.peek() == Some(&Token::If) {
+                        // else if chains nest inside the else block
+                        vec![self.statement()?]
+                    } else {
+                        self.block()?
+                    }
+                } else {
+                    vec![]
+                };
+                Ok(Stmt::If(cond, then_block, else_block))
+            }
+            Some(Token::While) => {
+                let cond = self.expr()?;
+                let body = self.block()?;
+                Ok(Stmt::While(cond, body))
+            }
+            Some(Token::Stop) => {
+                self.expect(Token::Semi)?;
+                Ok(Stmt::Stop)
+            }
+            Some(token) => {
+                self.pos -= 1;
+                self.error(format!("expected a statement, found {:?}", token))
+            }
+            None => self.error("expected a statement, found end of input".to_string()),
+        }
+    }
+
+    fn block(&mut self) -> Result<Vec<Stmt>, CompileError> {
+        self.expect(Token::LBrace)?;
+        let mut stmts = vec![];
+        while self.peek() != Some(&Token::RBrace) {
+            if self.peek().is_none() {
+                return self.error("unclosed block".to_string());
+            }
+            stmts.push(self.statement()?);
+        }
+        self.pos += 1;
+        Ok(stmts)
+    }
+
+    fn expr(&mut self) -> Result<Expr, CompileError> {
+        let lhs = self.additive()?;
+        let op = match self.peek() {
+            Some(Token::Lt) => BinOp::Lt,
+            Some(Token::Gt) => BinOp::Gt,
+            Some(Token::Le) => BinOp::Le,
+            Some(Token::Ge) => BinOp::Ge,
+            Some(Token::EqEq) => BinOp::Eq,
+            Some(Token::NotEq) => BinOp::NotEq,
+            _ => return Ok(lhs),
+        };
+        self.pos += 1;
+        let rhs = self.additive()?;
+        Ok(Expr::Binary(op, Box::new(lhs), Box::new(rhs)))
+    }
+
+    fn additive(&mut self) -> Result<Expr, CompileError> {
+        let mut lhs = self.term()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::Plus) => BinOp::Add,
+                Some(Token::Minus) => BinOp::Sub,
+                _ => return Ok(lhs),
+            };
+            self.pos += 1;
+            let rhs = self.term()?;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+    }
+
+    fn term(&mut self) -> Result<Expr, CompileError> {
+        let mut lhs = self.unary()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::Star) => BinOp::Mul,
+                Some(Token::Slash) => BinOp::Div,
+                _ => return Ok(lhs),
+            };
+            self.pos += 1;
+            let rhs = self.unary()?;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr, CompileError> {
+        if self.peek() == Some(&Token::Not) {
+            self.pos += 1;
+            return Ok(Expr::Not(Box::new(self.unary()?)));
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Expr, CompileError> {
+        let line = self.line();
+        match self.next() {
+            Some(Token::Num(num)) => Ok(Expr::Num(num)),
+            Some(Token::Ident(name)) => Ok(Expr::Var(name, line)),
+            Some(Token::LParen) => {
+                let expr = self.expr()?;
+                self.expect(Token::RParen)?;
+                Ok(expr)
+            }
+            Some(token) => {
+                self.pos -= 1;
+                self.error(format!("expected an expression, found {:?}", token))
+            }
+            None => self.error("expected an expression, found end of input".to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{super::lexer::tokenize, parse, BinOp, Expr, Stmt, ValueOp};
+
+    #[test]
+    fn test_precedence() {
+        let program = parse(&tokenize("value = 1 + 2 * 3 < 9;").unwrap()).unwrap();
+        let product = Expr::Binary(BinOp::Mul, Box::new(Expr::Num(2)), Box::new(Expr::Num(3)));
+        let sum = Expr::Binary(BinOp::Add, Box::new(Expr::Num(1)), Box::new(product));
+        assert_eq!(
+            program,
+            vec![Stmt::Value(
+                ValueOp::Set,
+                Expr::Binary(BinOp::Lt, Box::new(sum), Box::new(Expr::Num(9)))
+            )]
+        );
+    }
+
+    #[test]
+    fn test_syntax_errors() {
+        let err = parse(&tokenize("let x = 1;\nif x { value = 2; ").unwrap()).unwrap_err();
+        assert_eq!(err.line, Some(2));
+        assert!(parse(&tokenize("let = 4;").unwrap()).is_err());
+        assert!(parse(&tokenize("value * 4;").unwrap()).is_err());
+        assert!(parse(&tokenize("x = (1 + 2;").unwrap()).is_err());
+    }
+}
@@ -2,14 +2,24 @@
 extern crate serde_derive;
 
 extern crate bincode;
+#[cfg(test)]
+extern crate proptest;
 extern crate rand;
 extern crate ring;
 extern crate secp256k1;
 extern crate serde;
 extern crate sha3;
 
+pub mod cli;
 pub mod eth;
+pub mod lang;
 
 pub fn run() {
-    eth::benchutils::run_benchmarks();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.is_empty() {
+        eth::benchutils::run_benchmarks();
+    } else if let Err(msg) = cli::run(&args) {
+        eprintln!("{}", msg);
+        std::process::exit(1);
+    }
 }